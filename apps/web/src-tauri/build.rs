@@ -1,3 +1,18 @@
 fn main() {
+  // Bake the commit into `app_version`. CI can set KOMPOSE_GIT_COMMIT
+  // directly; local builds fall back to asking git.
+  println!("cargo:rerun-if-env-changed=KOMPOSE_GIT_COMMIT");
+  if std::env::var("KOMPOSE_GIT_COMMIT").is_err() {
+    let commit = std::process::Command::new("git")
+      .args(["rev-parse", "--short", "HEAD"])
+      .output()
+      .ok()
+      .filter(|output| output.status.success())
+      .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(commit) = commit {
+      println!("cargo:rustc-env=KOMPOSE_GIT_COMMIT={}", commit.trim());
+    }
+  }
+
   tauri_build::build()
 }
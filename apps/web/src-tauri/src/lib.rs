@@ -1,248 +1,5548 @@
-use std::sync::Mutex;
-use tauri::Manager;
+use serde::Serialize;
+#[cfg(desktop)]
+use std::collections::HashMap;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+#[cfg(desktop)]
+use tauri_plugin_autostart::ManagerExt;
+use tauri_plugin_clipboard_manager::ClipboardExt;
 use tauri_plugin_deep_link::DeepLinkExt;
 #[cfg(desktop)]
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_store::{Store, StoreExt};
+use tauri_plugin_updater::UpdaterExt;
+#[cfg(target_os = "windows")]
+use window_vibrancy::{apply_acrylic, apply_mica};
 #[cfg(target_os = "macos")]
 use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial, NSVisualEffectState};
 
 const MAIN_WINDOW_LABEL: &str = "main";
 const COMMAND_BAR_WINDOW_LABEL: &str = "command-bar";
 const COMMAND_BAR_WINDOW_ROUTE: &str = "/desktop/command-bar";
+#[cfg(target_os = "macos")]
+const ACCESSIBILITY_SETTINGS_URL: &str =
+    "x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility";
+const TRAY_ICON_ID: &str = "kompose-tray";
+const TRAY_MENU_OPEN_COMMAND_BAR_ID: &str = "tray-open-command-bar";
+const TRAY_MENU_SHOW_MAIN_WINDOW_ID: &str = "tray-show-main-window";
+const TRAY_MENU_QUIT_ID: &str = "tray-quit";
 const DEFAULT_SHORTCUT_PRESET_ID: &str = "cmd_or_ctrl_shift_k";
+/// Built-in presets (id, label) understood by `shortcut_for_preset`, in the
+/// order the settings UI lists them.
+#[cfg(desktop)]
+const SHORTCUT_PRESETS: &[(&str, &str)] = &[
+    ("cmd_or_ctrl_shift_k", "Cmd/Ctrl + Shift + K"),
+    ("ctrl_space", "Ctrl + Space"),
+    ("alt_space", "Alt/Option + Space"),
+];
+/// User-defined bindings are stored as preset ids of the form
+/// `custom:ctrl+alt+KeyJ` so they persist and restore like the presets.
+const CUSTOM_SHORTCUT_PRESET_PREFIX: &str = "custom:";
+/// What `get_active_shortcut_display` returns when no shortcut is live.
+#[cfg(desktop)]
+const SHORTCUT_DISABLED_DISPLAY: &str = "Disabled";
+#[cfg(desktop)]
+const SHORTCUT_UNREGISTERED_DISPLAY: &str = "Not registered";
+const COMMAND_BAR_DEFAULT_WIDTH: f64 = 480.0;
+const COMMAND_BAR_DEFAULT_HEIGHT: f64 = 56.0;
+const COMMAND_BAR_MIN_WIDTH: f64 = 320.0;
+const COMMAND_BAR_MAX_WIDTH: f64 = 960.0;
+const COMMAND_BAR_MIN_HEIGHT: f64 = 56.0;
+const COMMAND_BAR_MAX_HEIGHT: f64 = 600.0;
+/// Whole-window opacity range for `set_command_bar_opacity`; below the
+/// minimum the bar becomes hard to read.
+const COMMAND_BAR_MIN_OPACITY: f64 = 0.3;
+const COMMAND_BAR_MAX_OPACITY: f64 = 1.0;
+/// Wait between reactivating the previous app and posting a synthesized
+/// keystroke to it, so the event doesn't land in Kompose.
+#[cfg(target_os = "macos")]
+const KEYSTROKE_ACTIVATION_DELAY: Duration = Duration::from_millis(80);
+/// Length of the macOS fade when the command bar opens or closes.
+#[cfg(target_os = "macos")]
+const COMMAND_BAR_ANIMATION_DURATION: Duration = Duration::from_millis(120);
+/// Logical gap above the command bar for the `"top"` position.
+const COMMAND_BAR_TOP_MARGIN: f64 = 48.0;
+/// Logical gap between the pointer and the command bar for the `"cursor"`
+/// anchor, so the bar doesn't cover what the pointer is on.
+const COMMAND_BAR_CURSOR_OFFSET: f64 = 16.0;
+/// Focus-lost events this soon after showing are ignored; Windows fires a
+/// spurious one during the `show()`/`set_focus()` sequence.
+const COMMAND_BAR_FOCUS_LOSS_GRACE: Duration = Duration::from_millis(150);
+/// Shortcut presses closer together than this are treated as key repeat and
+/// dropped, so mashing the shortcut can't flip the bar shown/hidden at random.
+/// Short enough that a deliberate double toggle still goes through.
+const COMMAND_BAR_TOGGLE_DEBOUNCE: Duration = Duration::from_millis(100);
+/// How long the command bar may sit hidden before its webview is destroyed
+/// to free memory. It's recreated on the next open.
+const COMMAND_BAR_IDLE_TEARDOWN_AFTER: Duration = Duration::from_secs(10 * 60);
+const COMMAND_BAR_IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Shared with the frontend settings helpers in `src/lib/tauri-desktop.ts`.
+const SETTINGS_STORE_FILE: &str = "desktop-settings.json";
+/// Store the frontend keeps the bearer token in.
+const AUTH_STORE_FILE: &str = "auth.json";
+const SHORTCUT_PRESET_STORE_KEY: &str = "command-bar-shortcut-preset-id";
+/// Every bound preset; the primary one is mirrored into
+/// `SHORTCUT_PRESET_STORE_KEY` for the frontend.
+const SHORTCUT_PRESET_IDS_STORE_KEY: &str = "command-bar-shortcut-preset-ids";
+const SHORTCUT_ENABLED_STORE_KEY: &str = "command-bar-shortcut-enabled";
+/// `"center"`, `"top"`, or `{ "x": .., "y": .. }` in physical pixels.
+const COMMAND_BAR_POSITION_STORE_KEY: &str = "command-bar-position";
+const COMMAND_BAR_ANCHOR_STORE_KEY: &str = "command-bar-anchor";
+const COMMAND_BAR_WIDTH_STORE_KEY: &str = "command-bar-width";
+const COMMAND_BAR_HEIGHT_STORE_KEY: &str = "command-bar-height";
+const COMMAND_BAR_ALWAYS_ON_TOP_STORE_KEY: &str = "command-bar-always-on-top";
+const COMMAND_BAR_OPACITY_STORE_KEY: &str = "command-bar-opacity";
+const COMMAND_BAR_ANIMATIONS_STORE_KEY: &str = "command-bar-animations-enabled";
+const COMMAND_BAR_CLOSE_AFTER_ACTION_STORE_KEY: &str = "commandBar.closeAfterAction";
+const COMMAND_BAR_CAPTURE_EXCLUDED_STORE_KEY: &str = "command-bar-capture-excluded";
+const PRESENTATION_MODE_STORE_KEY: &str = "presentation-mode";
+const ONBOARDING_COMPLETED_STORE_KEY: &str = "onboarding-completed";
+const SHORTCUT_PROFILES_STORE_KEY: &str = "shortcut-profiles";
+const DEFAULT_SHORTCUT_PROFILE_NAME: &str = "default";
+const SHORTCUT_PROFILE_NAME_MAX_LEN: usize = 40;
+const RECENT_COMMANDS_STORE_KEY: &str = "recent-commands";
+const HISTORY_MAX_ENTRIES_STORE_KEY: &str = "history.maxEntries";
+/// Most command ids kept in the usage history, overridable through
+/// `history.maxEntries`; the least recently used beyond this are pruned on
+/// each write.
+const RECENT_COMMANDS_DEFAULT_MAX: usize = 200;
+/// Caps `set_history_max_entries` accepts.
+const HISTORY_MAX_ENTRIES_RANGE: std::ops::RangeInclusive<usize> = 10..=5000;
+/// Commands not used for this long drop out of the history entirely.
+const RECENT_COMMAND_MAX_AGE: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+const RECENT_COMMAND_ID_MAX_LEN: usize = 200;
+/// Settings captured when saving a shortcut profile and restored on apply:
+/// the shortcut plus the command bar's window layout.
+const SHORTCUT_PROFILE_SETTING_KEYS: &[&str] = &[
+    SHORTCUT_PRESET_STORE_KEY,
+    SHORTCUT_PRESET_IDS_STORE_KEY,
+    COMMAND_BAR_POSITION_STORE_KEY,
+    COMMAND_BAR_ANCHOR_STORE_KEY,
+    COMMAND_BAR_WIDTH_STORE_KEY,
+    COMMAND_BAR_HEIGHT_STORE_KEY,
+];
+// Probe the updater host: if it's unreachable, update checks and the OAuth
+// round-trip through the browser won't work either.
+const NETWORK_PROBE_ADDRESS: &str = "github.com:443";
+const NETWORK_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+const NETWORK_STATUS_CACHE_TTL: Duration = Duration::from_secs(30);
+/// URL/query keys whose values are secrets (OAuth callback tokens, link
+/// tokens, CSRF state) and must never leave the machine in exported logs.
+const REDACTED_PARAM_KEYS: &[&str] = &["token", "link_token", "state", "code"];
+/// Most deep links buffered before the frontend drains the queue; the oldest
+/// are dropped beyond this so a frontend that never mounts can't grow it.
+const PENDING_DEEP_LINKS_MAX: usize = 32;
+/// File name `export_logs` suggests in its save dialog.
+const EXPORTED_LOG_FILE_NAME: &str = "kompose.log";
+const REDACTED_PLACEHOLDER: &str = "[REDACTED]";
+/// Schemes `open_external` will hand to the OS; anything else (`file:`,
+/// custom app handlers) could launch arbitrary local programs.
+const OPEN_EXTERNAL_ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto"];
+/// Rotate the log file past this size, keeping only a few old files so the
+/// log directory stays at a handful of MB.
+const LOG_FILE_MAX_BYTES: u128 = 2 * 1024 * 1024;
+const LOG_FILES_KEPT: usize = 3;
+const TRAY_ENABLED_STORE_KEY: &str = "tray-enabled";
+const AUTOSTART_ENABLED_STORE_KEY: &str = "autostart-enabled";
+const NOTIFICATIONS_ENABLED_STORE_KEY: &str = "notifications-enabled";
+const DOCK_ICON_VISIBLE_STORE_KEY: &str = "dock-icon-visible";
+/// Passed by the OS login item so a login launch starts with only the
+/// command bar ready and no main window.
+const AUTOSTART_MINIMIZED_ARG: &str = "--minimized";
+const UPDATE_CHECK_INTERVAL_STORE_KEY: &str = "update-check-interval-hours";
+const DEFAULT_UPDATE_CHECK_INTERVAL_HOURS: u64 = 24;
+const MAX_UPDATE_CHECK_INTERVAL_HOURS: u64 = 24 * 30;
+/// Unix millis of the last completed background update check, so the
+/// schedule carries across restarts instead of restarting the interval.
+const UPDATE_LAST_CHECKED_AT_STORE_KEY: &str = "update-last-checked-at";
+/// `false` stops background update checks; on-demand checks still work.
+const AUTO_UPDATE_ENABLED_STORE_KEY: &str = "auto-update-enabled";
+/// Quiet period after the last high-frequency setting write (drag, resize)
+/// before the settings store is written to disk.
+const SETTINGS_SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Lock `mutex`, taking the data back if a panic poisoned it. The managed
+/// state is plain values that are never left half-updated, so recovering is
+/// safe and keeps one panic from failing every later shortcut change, deep
+/// link, or update check.
+fn lock_recovering<'a, T>(mutex: &'a Mutex<T>, name: &str) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log::warn!("Recovering {} after a panic poisoned its lock.", name);
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+// Names for `lock_recovering` warnings about `CommandBarShortcutState` fields.
+const PREVIOUS_APP_STATE: &str = "previous app state";
+const SHOWN_AT_STATE: &str = "command bar shown-at state";
+const STICKY_STATE: &str = "command bar sticky state";
+const TOGGLED_AT_STATE: &str = "command bar toggle state";
+#[cfg(desktop)]
+const SHORTCUT_ACTIONS_STATE: &str = "shortcut action state";
+const SHORTCUT_MODE_STATE: &str = "command bar shortcut mode state";
+const HIDDEN_SINCE_STATE: &str = "command bar hidden-since state";
+const OPEN_SIZE_STATE: &str = "command bar size state";
+const CREATION_FAILED_STATE: &str = "command bar creation state";
+const FRONTEND_READY_STATE: &str = "command bar frontend state";
+#[cfg(target_os = "macos")]
+const FADE_STATE: &str = "command bar fade state";
+// ...and about the other managed state behind a mutex.
+const OAUTH_STATE: &str = "OAuth state";
+const DEEP_LINK_STATE: &str = "deep link queue";
+const UPDATE_SCHEDULE_STATE: &str = "update schedule state";
+const UPDATER_STATE: &str = "updater state";
 
 struct CommandBarShortcutState {
-    active_preset: Mutex<String>,
+    /// Presets bound to the command bar, all registered at once. Never empty;
+    /// the first is the primary binding shown in the UI.
+    active_presets: Mutex<Vec<String>>,
+    /// Whether the command bar shortcuts should be registered at all. While
+    /// disabled `active_presets` is kept so re-enabling restores them.
+    enabled: Mutex<bool>,
     /// PID of the app that was frontmost before the command bar opened.
     /// On dismiss we reactivate this app so focus returns there (e.g. browser)
     /// instead of falling through to the main Kompose window.
     #[cfg(target_os = "macos")]
     previous_frontmost_pid: Mutex<i32>,
+    /// Localized name of that app, for "Acting on: Safari" style context.
+    #[cfg(target_os = "macos")]
+    previous_frontmost_app_name: Mutex<Option<String>>,
+    /// Raw HWND of the window that was in the foreground before the command
+    /// bar opened (0 when unknown). Stored as an integer since `HWND` isn't
+    /// `Send`.
+    #[cfg(target_os = "windows")]
+    previous_foreground_window: Mutex<isize>,
+    /// X11 id of the window that was active before the command bar opened
+    /// (0 when unknown or on Wayland, where it can't be queried).
+    #[cfg(target_os = "linux")]
+    previous_active_window: Mutex<u32>,
+    /// Outcome of the most recent command bar shortcut registration, so the
+    /// settings UI can tell the user when another app owns the shortcut.
+    registration: Mutex<ShortcutRegistration>,
+    /// When the command bar was last shown, for debouncing focus-lost hides.
+    last_shown_at: Mutex<Option<Instant>>,
+    /// Set by `set_command_bar_sticky` while a flow (e.g. OAuth in the
+    /// browser) deliberately moves focus away; suppresses the focus-lost
+    /// auto-hide. Cleared whenever the bar hides.
+    sticky: Mutex<bool>,
+    /// When a shortcut press last toggled the command bar.
+    last_toggled_at: Mutex<Option<Instant>>,
+    /// What each registered global shortcut does, consulted by the shortcut
+    /// handler. Kept in sync by the register/unregister helpers.
+    #[cfg(desktop)]
+    shortcut_actions: Mutex<HashMap<Shortcut, ShortcutAction>>,
+    /// When set, command bar shortcut presses emit `shortcut://triggered` to
+    /// the main window instead of toggling the bar, so the frontend decides
+    /// what the hotkey does. Off by default.
+    shortcut_emits_event: Mutex<bool>,
+    /// When the command bar was last hidden; `None` while visible or never
+    /// opened. Drives the idle teardown.
+    hidden_since: Mutex<Option<Instant>>,
+    /// One-shot size for the next open, set via `set_next_open_size`.
+    next_open_size: Mutex<Option<(f64, f64)>>,
+    /// Whether the window is currently sized by a one-shot override and
+    /// must go back to the default size on the following open.
+    open_size_overridden: Mutex<bool>,
+    /// Set when building the command bar webview failed (seen on some
+    /// WebKitGTK setups); the next open retries instead of giving up.
+    creation_failed: Mutex<bool>,
+    /// Set by `command_bar_frontend_ready` once the command bar page handles
+    /// Escape itself; until then Escape is bound natively while it's focused.
+    /// Cleared whenever the page (re)loads.
+    frontend_ready: Mutex<bool>,
+    /// Bumped by every fade-out and by a show that cancels one; a finishing
+    /// fade-out only hides the bar if the generation is still its own.
+    #[cfg(target_os = "macos")]
+    fade_generation: Mutex<u64>,
+    /// Whether a fade-out is in flight, so toggling during it reopens the bar
+    /// instead of starting a second fade.
+    #[cfg(target_os = "macos")]
+    fading_out: Mutex<bool>,
 }
 
 impl Default for CommandBarShortcutState {
     fn default() -> Self {
         Self {
-            active_preset: Mutex::new(DEFAULT_SHORTCUT_PRESET_ID.to_string()),
+            active_presets: Mutex::new(vec![DEFAULT_SHORTCUT_PRESET_ID.to_string()]),
+            enabled: Mutex::new(true),
             #[cfg(target_os = "macos")]
             previous_frontmost_pid: Mutex::new(-1),
+            #[cfg(target_os = "macos")]
+            previous_frontmost_app_name: Mutex::new(None),
+            #[cfg(target_os = "windows")]
+            previous_foreground_window: Mutex::new(0),
+            #[cfg(target_os = "linux")]
+            previous_active_window: Mutex::new(0),
+            registration: Mutex::new(ShortcutRegistration::default()),
+            last_shown_at: Mutex::new(None),
+            sticky: Mutex::new(false),
+            last_toggled_at: Mutex::new(None),
+            #[cfg(desktop)]
+            shortcut_actions: Mutex::new(HashMap::new()),
+            shortcut_emits_event: Mutex::new(false),
+            hidden_since: Mutex::new(None),
+            next_open_size: Mutex::new(None),
+            open_size_overridden: Mutex::new(false),
+            creation_failed: Mutex::new(false),
+            frontend_ready: Mutex::new(false),
+            #[cfg(target_os = "macos")]
+            fade_generation: Mutex::new(0),
+            #[cfg(target_os = "macos")]
+            fading_out: Mutex::new(false),
         }
     }
 }
 
-/// Returns the PID of the currently frontmost (active) application.
-#[cfg(target_os = "macos")]
-fn get_frontmost_app_pid() -> i32 {
-    use objc::runtime::Object;
-    use objc::{class, msg_send, sel, sel_impl};
-    unsafe {
-        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
-        let app: *mut Object = msg_send![workspace, frontmostApplication];
-        if app.is_null() {
-            return -1;
+impl CommandBarShortcutState {
+    fn lock_active_presets(&self) -> MutexGuard<'_, Vec<String>> {
+        lock_recovering(&self.active_presets, "command bar preset state")
+    }
+
+    fn lock_enabled(&self) -> MutexGuard<'_, bool> {
+        lock_recovering(&self.enabled, "command bar shortcut enabled state")
+    }
+
+    fn lock_registration(&self) -> MutexGuard<'_, ShortcutRegistration> {
+        lock_recovering(&self.registration, "command bar registration state")
+    }
+
+    /// Put the user-configurable fields back to their `Default` values for
+    /// `reset_settings`. Runtime bookkeeping (previous app, show/hide
+    /// timestamps) is left as-is since it tracks windows that still exist.
+    fn reset_to_defaults(&self) {
+        let defaults = Self::default();
+        *self.lock_active_presets() = defaults.active_presets.into_inner().unwrap_or_default();
+        *self.lock_enabled() = true;
+        *lock_recovering(&self.shortcut_emits_event, SHORTCUT_MODE_STATE) = false;
+        *lock_recovering(&self.sticky, STICKY_STATE) = false;
+        *self.lock_registration() = ShortcutRegistration::default();
+        *lock_recovering(&self.next_open_size, OPEN_SIZE_STATE) = None;
+        *lock_recovering(&self.open_size_overridden, OPEN_SIZE_STATE) = false;
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionInfo {
+    version: String,
+    /// Short git commit the build was made from, when known.
+    commit: Option<&'static str>,
+    os: &'static str,
+    arch: &'static str,
+}
+
+/// Snapshot of app state for the troubleshooting panel and bug reports.
+/// Probes that fail are `None` with the reason in `errors`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Diagnostics {
+    version: VersionInfo,
+    shortcut: Option<ShortcutStatus>,
+    deep_link_registered: Option<bool>,
+    autostart_enabled: Option<bool>,
+    /// macOS Accessibility permission; always true elsewhere.
+    accessibility_trusted: bool,
+    command_bar_exists: bool,
+    command_bar_visible: bool,
+    errors: Vec<String>,
+}
+
+/// The app that was frontmost before the command bar opened.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+struct AppContext {
+    name: Option<String>,
+    pid: i32,
+}
+
+/// A regular (Dock-visible) app for the command bar's app switcher.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+struct RunningApp {
+    name: String,
+    bundle_id: Option<String>,
+    pid: i32,
+}
+
+/// Where the command bar opens, from the `command-bar-position` setting.
+#[cfg(desktop)]
+#[derive(Clone, Copy, PartialEq)]
+enum CommandBarPosition {
+    /// Centered horizontally, a third of the way down the active monitor.
+    Center,
+    /// Centered horizontally near the top of the active monitor.
+    Top,
+    /// Physical screen coordinates saved after the user dragged the bar.
+    Saved { x: i32, y: i32 },
+}
+
+/// What a registered global shortcut does when pressed.
+#[cfg(desktop)]
+#[derive(Clone, Copy, PartialEq)]
+enum ShortcutAction {
+    /// One of the command bar presets: toggle the bar (or emit
+    /// `shortcut://triggered` when the frontend asked for that).
+    ToggleCommandBar,
+    /// Show or hide the main window.
+    ToggleMainWindow,
+    /// Escape while the command bar has focus.
+    DismissCommandBar,
+}
+
+/// Which screen area the command bar opens relative to, from the
+/// `command-bar-anchor` setting.
+#[cfg(desktop)]
+#[derive(Clone, Copy, PartialEq)]
+enum CommandBarAnchor {
+    /// The primary monitor, wherever the cursor is.
+    ScreenCenter,
+    /// The monitor under the cursor.
+    MonitorCenter,
+    /// Just below (or above, near the bottom edge) the cursor itself.
+    Cursor,
+}
+
+#[derive(Clone, Default)]
+struct ShortcutRegistration {
+    registered: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShortcutStatus {
+    active_preset: String,
+    active_presets: Vec<String>,
+    enabled: bool,
+    registered: bool,
+    error: Option<String>,
+}
+
+/// Error returned by the shortcut commands, serialized as
+/// `{ "code": "shortcut-unsupported", "message": "..." }` so the UI can
+/// branch on `code` and still show `message` as-is.
+#[derive(Debug)]
+#[cfg_attr(not(desktop), allow(dead_code))]
+enum KomposeError {
+    /// The preset or key combination can't be used for the command bar.
+    ShortcutUnsupported(String),
+    /// The OS refused the binding, usually because another app holds it.
+    ShortcutRegistrationFailed(String),
+    /// The window with this label doesn't exist.
+    WindowMissing(&'static str),
+    /// Anything else, e.g. failing to persist a setting.
+    Other(String),
+}
+
+impl KomposeError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::ShortcutUnsupported(_) => "shortcut-unsupported",
+            Self::ShortcutRegistrationFailed(_) => "shortcut-registration-failed",
+            Self::WindowMissing(_) => "window-missing",
+            Self::Other(_) => "other",
         }
-        msg_send![app, processIdentifier]
     }
 }
 
-/// Hides the entire application and activates the previously active app.
-/// This is the macOS equivalent of Cmd+H and is atomic — no intermediate
-/// state where the main window is visible, so there's no flicker.
-#[cfg(target_os = "macos")]
-fn hide_app() {
-    use objc::runtime::Object;
-    use objc::{class, msg_send, sel, sel_impl};
-    unsafe {
-        let ns_app: *mut Object = msg_send![class!(NSApplication), sharedApplication];
-        let _: () = msg_send![ns_app, hide: std::ptr::null::<Object>()];
+impl std::fmt::Display for KomposeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShortcutUnsupported(message)
+            | Self::ShortcutRegistrationFailed(message)
+            | Self::Other(message) => f.write_str(message),
+            Self::WindowMissing(label) => write!(f, "The '{}' window is not available.", label),
+        }
     }
 }
 
-#[cfg(desktop)]
-fn primary_modifier() -> Modifiers {
-    #[cfg(target_os = "macos")]
-    {
-        return Modifiers::SUPER;
+impl std::error::Error for KomposeError {}
+
+impl Serialize for KomposeError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut error = serializer.serialize_struct("KomposeError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
     }
-    #[cfg(not(target_os = "macos"))]
-    {
-        return Modifiers::CONTROL;
+}
+
+impl From<String> for KomposeError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
     }
 }
 
-#[cfg(desktop)]
-fn shortcut_for_preset(preset_id: &str) -> Option<Shortcut> {
-    let primary = primary_modifier();
+/// Deep-link URLs and OAuth callbacks received before the frontend's
+/// `DeepLinkHandler` mounted (e.g. a cold start from an OAuth callback),
+/// drained once on mount. Once drained, later ones are emitted live instead
+/// of buffered.
+#[derive(Default)]
+struct DeepLinkState {
+    queue: Mutex<DeepLinkQueue>,
+}
 
-    let shortcut = match preset_id {
-        "cmd_or_ctrl_shift_k" => Shortcut::new(Some(primary | Modifiers::SHIFT), Code::KeyK),
-        "ctrl_space" => Shortcut::new(Some(Modifiers::CONTROL), Code::Space),
-        "alt_space" => Shortcut::new(Some(Modifiers::ALT), Code::Space),
-        _ => return None,
-    };
+#[derive(Default)]
+struct DeepLinkQueue {
+    pending_urls: Vec<String>,
+    pending_auth_callbacks: Vec<AuthCallback>,
+    frontend_ready: bool,
+}
 
-    Some(shortcut)
+/// What `take_pending_deep_links` hands the frontend on mount.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingDeepLinks {
+    urls: Vec<String>,
+    auth_callbacks: Vec<AuthCallback>,
 }
 
+/// CSRF `state` handed out by `begin_oauth`, checked (and consumed) when the
+/// matching `auth/callback` deep link arrives.
+#[derive(Default)]
+struct OAuthState {
+    pending_state: Mutex<Option<String>>,
+}
+
+/// Parsed `<scheme>://auth/callback` deep link, emitted as `auth://callback`.
+/// `token` is only set when `state` matched the one from `begin_oauth`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthCallback {
+    token: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    /// `"link"` for an account-link flow, absent for sign-in.
+    mode: Option<String>,
+    /// OAuth provider the flow went through, e.g. `"google"`.
+    provider: Option<String>,
+}
+
+/// When the newest not-yet-saved settings write happened, for
+/// `schedule_settings_save`. `None` once everything is on disk.
+#[derive(Default)]
+struct SettingsSaveState {
+    last_write: Mutex<Option<Instant>>,
+}
+
+/// Most recent reachability probe, reused for `NETWORK_STATUS_CACHE_TTL` so
+/// repeated checks don't each open a socket.
+#[derive(Default)]
+struct NetworkStatusState {
+    last_probe: Mutex<Option<(Instant, bool)>>,
+}
+
+/// Channel into the background update-check thread; sending a new interval
+/// (in hours, 0 = never) reschedules the next check.
+#[derive(Default)]
+struct UpdateScheduleState {
+    interval_sender: Mutex<Option<mpsc::Sender<u64>>>,
+}
+
+/// Update found by the last check, kept so installing doesn't need a second
+/// round-trip to the release feed.
+#[derive(Default)]
+struct UpdaterState {
+    pending_update: Mutex<Option<tauri_plugin_updater::Update>>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateInfo {
+    version: String,
+    current_version: String,
+    notes: Option<String>,
+}
+
+impl From<&tauri_plugin_updater::Update> for UpdateInfo {
+    fn from(update: &tauri_plugin_updater::Update) -> Self {
+        Self {
+            version: update.version.clone(),
+            current_version: update.current_version.clone(),
+            notes: update.body.clone(),
+        }
+    }
+}
+
+/// Payload of the `updater://progress` event.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateProgress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkStatus {
+    online: bool,
+}
+
+/// Resolved primary modifier for shortcut hints, so the UI never has to
+/// guess between ⌘ and Ctrl.
 #[cfg(desktop)]
-fn register_shortcut_preset(app: &tauri::AppHandle, preset_id: &str) -> Result<(), String> {
-    let shortcut = shortcut_for_preset(preset_id)
-        .ok_or_else(|| format!("Unsupported command bar shortcut preset '{}'.", preset_id))?;
-    app.global_shortcut().register(shortcut).map_err(|error| {
-        format!(
-            "Failed to register shortcut preset '{}': {}",
-            preset_id, error
-        )
-    })?;
-    Ok(())
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PrimaryModifierInfo {
+    /// Modifier token as accepted by the shortcut commands (`super`/`ctrl`).
+    name: &'static str,
+    label: &'static str,
+    glyph: &'static str,
 }
 
 #[cfg(desktop)]
-fn unregister_shortcut_preset(app: &tauri::AppHandle, preset_id: &str) {
-    let Some(shortcut) = shortcut_for_preset(preset_id) else {
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShortcutPreset {
+    id: String,
+    /// Preset name, or the binding itself for `custom:` presets.
+    label: String,
+    /// Platform-resolved binding, e.g. `⇧⌘K` on macOS or `Ctrl+Shift+K`.
+    shortcut: String,
+    registered: bool,
+}
+
+/// Payload of `shortcut://triggered`, identifying which binding fired.
+#[cfg(desktop)]
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShortcutTriggered {
+    /// Active preset the shortcut belongs to, e.g. `ctrl_space`.
+    preset_id: Option<String>,
+    /// Platform display string, e.g. `⇧⌘K`.
+    shortcut: String,
+}
+
+/// An app from `native_search`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResult {
+    name: String,
+    /// `.app` bundle (macOS), Start Menu shortcut (Windows), or desktop
+    /// entry (Linux); pass to `launch_path` to open it.
+    path: String,
+    /// Icon theme name from the desktop entry on Linux; `None` elsewhere.
+    icon_hint: Option<String>,
+}
+
+/// Payload of the `command-bar://visibility` event.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommandBarVisibility {
+    visible: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShortcutProfile {
+    name: String,
+    built_in: bool,
+}
+
+/// A command bar action from the usage history, as recorded by
+/// `record_command_usage`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecentCommand {
+    id: String,
+    use_count: u64,
+    /// Milliseconds since the Unix epoch.
+    last_used_at: u64,
+}
+
+fn settings_store(app: &tauri::AppHandle) -> Result<Arc<Store<tauri::Wry>>, String> {
+    app.store(SETTINGS_STORE_FILE)
+        .map_err(|error| format!("Failed to open settings store: {}", error))
+}
+
+fn save_settings_now(app: &tauri::AppHandle) {
+    let result = settings_store(app).and_then(|store| {
+        store
+            .save()
+            .map_err(|error| format!("Failed to save settings: {}", error))
+    });
+    if let Err(error) = result {
+        log::warn!("{}", error);
+    }
+}
+
+/// Save the settings store once writes have been quiet for
+/// `SETTINGS_SAVE_DEBOUNCE`, so commands fired on every drag frame coalesce
+/// into one disk write. Callers have already `set` the value, so reads see
+/// it immediately; save errors are only logged.
+fn schedule_settings_save(app: &tauri::AppHandle) {
+    let state = app.state::<SettingsSaveState>();
+    let Ok(mut last_write) = state.last_write.lock() else {
+        save_settings_now(app);
         return;
     };
-    if let Err(error) = app.global_shortcut().unregister(shortcut) {
-        log::warn!(
-            "Failed to unregister shortcut preset '{}': {}",
-            preset_id,
-            error
-        );
+    let flush_pending = last_write.is_some();
+    *last_write = Some(Instant::now());
+    drop(last_write);
+    if flush_pending {
+        return;
     }
+
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SETTINGS_SAVE_DEBOUNCE);
+        let state = app.state::<SettingsSaveState>();
+        let Ok(mut last_write) = state.last_write.lock() else {
+            return;
+        };
+        match *last_write {
+            // Already written by `flush_settings`.
+            None => return,
+            Some(written_at) if written_at.elapsed() < SETTINGS_SAVE_DEBOUNCE => continue,
+            Some(_) => *last_write = None,
+        }
+        drop(last_write);
+        save_settings_now(&app);
+        return;
+    });
 }
 
-#[cfg(desktop)]
-fn create_command_bar_window(app: &tauri::App) -> tauri::Result<()> {
-    if app.get_webview_window(COMMAND_BAR_WINDOW_LABEL).is_some() {
-        return Ok(());
+/// Write any settings still waiting on `schedule_settings_save` right away,
+/// e.g. before the app exits.
+fn flush_settings(app: &tauri::AppHandle) {
+    let pending = app
+        .state::<SettingsSaveState>()
+        .last_write
+        .lock()
+        .map(|mut guard| guard.take().is_some())
+        .unwrap_or(true);
+    if pending {
+        save_settings_now(app);
     }
+}
 
-    let command_bar_window_builder = tauri::WebviewWindowBuilder::new(
-        app,
-        COMMAND_BAR_WINDOW_LABEL,
-        tauri::WebviewUrl::App(COMMAND_BAR_WINDOW_ROUTE.into()),
-    )
-    .title("Kompose Command Bar")
-    .visible(false)
-    .focused(false)
-    .resizable(false)
-    .decorations(false)
-    .always_on_top(true)
-    .skip_taskbar(true)
-    .inner_size(480.0, 56.0);
+/// Best-effort online check: a TCP connect to the probe address. DNS failure
+/// counts as offline.
+fn probe_network_reachability() -> bool {
+    let Ok(addresses) = NETWORK_PROBE_ADDRESS.to_socket_addrs() else {
+        return false;
+    };
+    addresses
+        .take(2)
+        .any(|address| TcpStream::connect_timeout(&address, NETWORK_PROBE_TIMEOUT).is_ok())
+}
 
-    #[cfg(target_os = "macos")]
-    let command_bar_window_builder = command_bar_window_builder.transparent(true);
+/// Returns the cached online state, re-probing once the cache expires and
+/// emitting `network-status-changed` when the result flips. Blocks on the
+/// probe, so call it off the main thread.
+fn current_network_status(app: &tauri::AppHandle) -> bool {
+    let state = app.state::<NetworkStatusState>();
+    let previous = state.last_probe.lock().map(|guard| *guard).unwrap_or(None);
+    if let Some((probed_at, online)) = previous {
+        if probed_at.elapsed() < NETWORK_STATUS_CACHE_TTL {
+            return online;
+        }
+    }
 
-    let command_bar_window = command_bar_window_builder.build()?;
+    let online = probe_network_reachability();
+    if let Ok(mut guard) = state.last_probe.lock() {
+        *guard = Some((Instant::now(), online));
+    }
 
-    #[cfg(target_os = "macos")]
-    {
-        // Match the rounded command surface with a native macOS HUD material
-        // so the frameless popup keeps soft corners instead of a square shell.
-        if let Err(error) = apply_vibrancy(
-            &command_bar_window,
-            NSVisualEffectMaterial::HudWindow,
-            Some(NSVisualEffectState::Active),
-            Some(12.0),
-        ) {
-            log::warn!("Failed to apply command bar vibrancy: {}", error);
+    if previous.is_some_and(|(_, was_online)| was_online != online) {
+        if let Err(error) = app.emit("network-status-changed", NetworkStatus { online }) {
+            log::warn!("Failed to emit network status event: {}", error);
         }
     }
 
-    // Hide the popup when focus leaves the command bar window (e.g. user
-    // clicks another app). For programmatic Esc-dismiss the frontend calls
-    // the dismiss_command_bar command which activates the previous app first.
-    let window_handle = command_bar_window.clone();
-    command_bar_window.on_window_event(move |event| {
-        if let tauri::WindowEvent::Focused(false) = event {
-            let _ = window_handle.hide();
+    online
+}
+
+fn is_redacted_value_terminator(c: char) -> bool {
+    matches!(c, '&' | '#' | '"' | '\'' | ',' | ')' | ']' | '}') || c.is_whitespace()
+}
+
+/// Replace the values of secret `key=value` pairs (see `REDACTED_PARAM_KEYS`)
+/// anywhere in `text`, e.g. `kompose://auth/callback?token=abc` becomes
+/// `kompose://auth/callback?token=[REDACTED]`.
+fn redact_secrets(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(eq_index) = rest.find('=') {
+        let (head, tail) = rest.split_at(eq_index + 1);
+        redacted.push_str(head);
+        rest = tail;
+
+        let key = head[..eq_index]
+            .rsplit(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .next()
+            .unwrap_or("");
+        if !REDACTED_PARAM_KEYS
+            .iter()
+            .any(|secret_key| secret_key.eq_ignore_ascii_case(key))
+        {
+            continue;
+        }
+
+        let value_len = tail
+            .find(is_redacted_value_terminator)
+            .unwrap_or(tail.len());
+        if value_len > 0 {
+            redacted.push_str(REDACTED_PLACEHOLDER);
+        }
+        rest = &tail[value_len..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Most recently written `.log` file in the app log directory. The log plugin
+/// rotates files, so the newest one holds the current session.
+fn current_log_file(app: &tauri::AppHandle) -> Result<Option<PathBuf>, String> {
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|error| format!("Failed to resolve log directory: {}", error))?;
+    let Ok(entries) = std::fs::read_dir(&log_dir) else {
+        return Ok(None);
+    };
+
+    Ok(entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .filter_map(|path| {
+            let modified = path.metadata().ok()?.modified().ok()?;
+            Some((modified, path))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path))
+}
+
+fn stored_update_check_interval_hours(app: &tauri::AppHandle) -> u64 {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(UPDATE_CHECK_INTERVAL_STORE_KEY))
+        .and_then(|value| value.as_u64())
+        .filter(|hours| *hours <= MAX_UPDATE_CHECK_INTERVAL_HOURS)
+        .unwrap_or(DEFAULT_UPDATE_CHECK_INTERVAL_HOURS)
+}
+
+fn stored_update_last_checked_at(app: &tauri::AppHandle) -> Option<u64> {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(UPDATE_LAST_CHECKED_AT_STORE_KEY))
+        .and_then(|value| value.as_u64())
+}
+
+fn stored_auto_update_enabled(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(AUTO_UPDATE_ENABLED_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true)
+}
+
+/// Time until the next background check is due: `interval_hours` after the
+/// last completed one, or right away if that's overdue or never happened.
+fn update_check_delay(last_checked_at: Option<u64>, now: u64, interval_hours: u64) -> Duration {
+    let Some(last_checked_at) = last_checked_at else {
+        return Duration::ZERO;
+    };
+    let elapsed = Duration::from_millis(now.saturating_sub(last_checked_at));
+    Duration::from_secs(interval_hours * 60 * 60).saturating_sub(elapsed)
+}
+
+/// One background update check; emits `update-available` when the updater
+/// endpoint reports a newer release and records when it ran. Skipped while
+/// offline or with auto-update turned off.
+fn run_scheduled_update_check(app: &tauri::AppHandle) {
+    if !stored_auto_update_enabled(app) {
+        log::info!("Skipping scheduled update check; auto-update is off.");
+        return;
+    }
+    if !current_network_status(app) {
+        log::info!("Skipping scheduled update check while offline.");
+        return;
+    }
+
+    let result = tauri::async_runtime::block_on(async {
+        let updater = app.updater()?;
+        updater.check().await
+    });
+
+    if result.is_ok() {
+        if let Ok(store) = settings_store(app) {
+            store.set(UPDATE_LAST_CHECKED_AT_STORE_KEY, unix_millis_now());
+            schedule_settings_save(app);
+        }
+    }
+
+    match result {
+        Ok(Some(update)) => {
+            if let Err(error) = app.emit("update-available", UpdateInfo::from(&update)) {
+                log::warn!("Failed to emit update available event: {}", error);
+            }
+            let state = app.state::<UpdaterState>();
+            *lock_recovering(&state.pending_update, UPDATER_STATE) = Some(update);
+        }
+        Ok(None) => {}
+        Err(error) => log::warn!("Scheduled update check failed: {}", error),
+    }
+}
+
+/// Run update checks every `interval_hours` on a dedicated thread, timed from
+/// the last completed check so an overdue one runs at startup. The returned
+/// sender reschedules the loop; dropping it stops the thread.
+fn spawn_update_check_scheduler(app: tauri::AppHandle, interval_hours: u64) -> mpsc::Sender<u64> {
+    let (sender, receiver) = mpsc::channel::<u64>();
+    std::thread::spawn(move || {
+        let mut interval_hours = interval_hours;
+        let mut delay = update_check_delay(
+            stored_update_last_checked_at(&app),
+            unix_millis_now(),
+            interval_hours,
+        );
+        loop {
+            let next = if interval_hours == 0 {
+                receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                receiver.recv_timeout(delay)
+            };
+            match next {
+                Ok(next_interval_hours) => {
+                    interval_hours = next_interval_hours;
+                    delay = update_check_delay(
+                        stored_update_last_checked_at(&app),
+                        unix_millis_now(),
+                        interval_hours,
+                    );
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    run_scheduled_update_check(&app);
+                    // A skipped or failed check waits a full interval too.
+                    delay = Duration::from_secs(interval_hours * 60 * 60);
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+    sender
+}
+
+/// Query params of an `auth/callback` deep link (any scheme, so every app
+/// flavor is covered), or `None` for other URLs.
+fn parse_auth_callback(url: &str) -> Option<AuthCallback> {
+    let url = tauri::Url::parse(url).ok()?;
+    if url.host_str() != Some("auth") || url.path().trim_end_matches('/') != "/callback" {
+        return None;
+    }
+
+    let mut callback = AuthCallback {
+        token: None,
+        state: None,
+        error: None,
+        mode: None,
+        provider: None,
+    };
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "token" => callback.token = Some(value.into_owned()),
+            "state" => callback.state = Some(value.into_owned()),
+            "error" => callback.error = Some(value.into_owned()),
+            "mode" => callback.mode = Some(value.into_owned()),
+            "provider" => callback.provider = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    Some(callback)
+}
+
+/// Command bar control verbs accepted from `<scheme>://command-bar/<verb>`
+/// deep links, so external automation can drive the bar.
+#[derive(Clone, Copy, Debug)]
+enum CommandBarDeepLinkAction {
+    Open,
+    Close,
+    Toggle,
+}
+
+/// The control verb of a `command-bar/<verb>` deep link (any scheme, like
+/// `parse_auth_callback`), or `None` for other URLs. Only the exact verbs in
+/// the allowlist match, with no query or fragment.
+fn parse_command_bar_deep_link(url: &str) -> Option<CommandBarDeepLinkAction> {
+    let url = tauri::Url::parse(url).ok()?;
+    if url.host_str() != Some("command-bar") || url.query().is_some() || url.fragment().is_some() {
+        return None;
+    }
+    match url.path().trim_end_matches('/') {
+        "/open" => Some(CommandBarDeepLinkAction::Open),
+        "/close" => Some(CommandBarDeepLinkAction::Close),
+        "/toggle" => Some(CommandBarDeepLinkAction::Toggle),
+        _ => None,
+    }
+}
+
+/// Run a command bar control deep link.
+#[cfg(desktop)]
+fn run_command_bar_deep_link(app: &tauri::AppHandle, action: CommandBarDeepLinkAction) {
+    let result = match action {
+        CommandBarDeepLinkAction::Open => show_command_bar(app),
+        CommandBarDeepLinkAction::Close => hide_command_bar(app),
+        CommandBarDeepLinkAction::Toggle => toggle_command_bar_window(app),
+    };
+    if let Err(error) = result {
+        log::warn!(
+            "Failed to run command bar deep link {:?}: {}",
+            action,
+            error
+        );
+    }
+}
+
+/// The OAuth callbacks in `urls`, with the token dropped unless its `state`
+/// matches the pending one from `begin_oauth`.
+fn validated_auth_callbacks(app: &tauri::AppHandle, urls: &[String]) -> Vec<AuthCallback> {
+    let mut callbacks = Vec::new();
+    for mut callback in urls.iter().filter_map(|url| parse_auth_callback(url)) {
+        let state_matches = {
+            let oauth_state = app.state::<OAuthState>();
+            let mut pending = lock_recovering(&oauth_state.pending_state, OAUTH_STATE);
+            let matches = pending.is_some() && pending.as_ref() == callback.state.as_ref();
+            if matches {
+                *pending = None;
+            }
+            matches
+        };
+        if !state_matches {
+            log::warn!("Rejected OAuth callback with an unknown or missing state.");
+            callback.token = None;
+            callback.error = Some("OAuth state did not match the sign-in request.".to_string());
+        }
+        callbacks.push(callback);
+    }
+    callbacks
+}
+
+/// Drop the oldest entries beyond `PENDING_DEEP_LINKS_MAX`, returning how
+/// many were dropped.
+fn drop_oldest_pending<T>(pending: &mut Vec<T>) -> usize {
+    let overflow = pending.len().saturating_sub(PENDING_DEEP_LINKS_MAX);
+    pending.drain(..overflow);
+    overflow
+}
+
+/// Show a native notification unless the user turned notifications off.
+fn show_notification(app: &tauri::AppHandle, title: &str, body: &str) -> Result<(), String> {
+    let enabled = settings_store(app)
+        .ok()
+        .and_then(|store| store.get(NOTIFICATIONS_ENABLED_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true);
+    if !enabled {
+        return Ok(());
+    }
+
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|error| format!("Failed to show notification: {}", error))
+}
+
+fn permission_state_name(state: tauri_plugin_notification::PermissionState) -> &'static str {
+    match state {
+        tauri_plugin_notification::PermissionState::Granted => "granted",
+        tauri_plugin_notification::PermissionState::Denied => "denied",
+        _ => "prompt",
+    }
+}
+
+/// Paths under `dir` with extension `extension`, descending at most `depth`
+/// levels into other directories. Matching directories (e.g. `.app`
+/// bundles) are returned rather than entered.
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+fn collect_paths_with_extension(
+    dir: &std::path::Path,
+    extension: &str,
+    depth: usize,
+    paths: &mut Vec<PathBuf>,
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        if path.extension().is_some_and(|ext| ext == extension) {
+            paths.push(path);
+        } else if depth > 0 && path.is_dir() {
+            collect_paths_with_extension(&path, extension, depth - 1, paths);
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn search_result_from_file_stem(path: PathBuf) -> Option<SearchResult> {
+    Some(SearchResult {
+        name: path.file_stem()?.to_str()?.to_string(),
+        path: path.to_string_lossy().into_owned(),
+        icon_hint: None,
+    })
+}
+
+/// `.app` bundles in the standard application folders (and one level of
+/// subfolders such as `Utilities`).
+#[cfg(target_os = "macos")]
+fn installed_apps() -> Vec<SearchResult> {
+    let mut roots = vec![
+        PathBuf::from("/Applications"),
+        PathBuf::from("/System/Applications"),
+    ];
+    if let Some(home) = std::env::var_os("HOME") {
+        roots.push(PathBuf::from(home).join("Applications"));
+    }
+
+    let mut paths = Vec::new();
+    for root in &roots {
+        collect_paths_with_extension(root, "app", 1, &mut paths);
+    }
+    paths
+        .into_iter()
+        .filter_map(search_result_from_file_stem)
+        .collect()
+}
+
+/// Shortcuts in the machine-wide and per-user Start Menu.
+#[cfg(target_os = "windows")]
+fn installed_apps() -> Vec<SearchResult> {
+    let mut paths = Vec::new();
+    for base in ["ProgramData", "APPDATA"] {
+        if let Some(base) = std::env::var_os(base) {
+            let start_menu = PathBuf::from(base).join(r"Microsoft\Windows\Start Menu\Programs");
+            collect_paths_with_extension(&start_menu, "lnk", 3, &mut paths);
+        }
+    }
+    paths
+        .into_iter()
+        .filter_map(search_result_from_file_stem)
+        .collect()
+}
+
+/// Name and icon of a `.desktop` entry, skipping entries hidden from menus.
+#[cfg(target_os = "linux")]
+fn parse_desktop_entry(path: &std::path::Path) -> Option<SearchResult> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut in_desktop_entry = false;
+    let mut name = None;
+    let mut icon_hint = None;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_desktop_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_desktop_entry {
+            continue;
+        }
+        if line == "NoDisplay=true" || line == "Hidden=true" {
+            return None;
+        }
+        if let Some(value) = line.strip_prefix("Name=") {
+            name.get_or_insert_with(|| value.to_string());
+        } else if let Some(value) = line.strip_prefix("Icon=") {
+            icon_hint = Some(value.to_string());
+        }
+    }
+    Some(SearchResult {
+        name: name?,
+        path: path.to_string_lossy().into_owned(),
+        icon_hint,
+    })
+}
+
+/// Desktop entries from the system, Flatpak, and per-user application dirs.
+#[cfg(target_os = "linux")]
+fn installed_apps() -> Vec<SearchResult> {
+    let mut roots = vec![
+        PathBuf::from("/usr/share/applications"),
+        PathBuf::from("/usr/local/share/applications"),
+        PathBuf::from("/var/lib/flatpak/exports/share/applications"),
+    ];
+    if let Some(home) = std::env::var_os("HOME") {
+        roots.push(PathBuf::from(home).join(".local/share/applications"));
+    }
+
+    let mut paths = Vec::new();
+    for root in &roots {
+        collect_paths_with_extension(root, "desktop", 1, &mut paths);
+    }
+    paths
+        .iter()
+        .filter_map(|path| parse_desktop_entry(path))
+        .collect()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn installed_apps() -> Vec<SearchResult> {
+    Vec::new()
+}
+
+/// Case-insensitive match score of `query` against `candidate`: prefix
+/// matches rank above word-start matches, then other substrings, then
+/// scattered in-order characters (penalized by the gaps between them).
+/// `None` when the query's characters don't all appear in order.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query = query.to_lowercase();
+    let candidate = candidate.to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+    if candidate.starts_with(&query) {
+        return Some(3000 - candidate.len() as i64);
+    }
+    if let Some(index) = candidate.find(&query) {
+        let word_start = candidate[..index].ends_with(|c: char| !c.is_alphanumeric());
+        let base = if word_start { 2000 } else { 1000 };
+        return Some(base - index as i64);
+    }
+
+    let mut candidate_chars = candidate.chars().enumerate();
+    let mut previous_position: Option<usize> = None;
+    let mut gap_penalty = 0;
+    for query_char in query.chars() {
+        let (position, _) = candidate_chars.find(|(_, c)| *c == query_char)?;
+        if let Some(previous_position) = previous_position {
+            gap_penalty += (position - previous_position - 1) as i64;
+        }
+        previous_position = Some(position);
+    }
+    Some(500 - gap_penalty)
+}
+
+fn theme_name(theme: tauri::Theme) -> &'static str {
+    match theme {
+        tauri::Theme::Dark => "dark",
+        _ => "light",
+    }
+}
+
+/// Emit `theme://changed` (`"dark"`/`"light"`) to `window` whenever the OS
+/// appearance flips. Each window forwards its own event, so this also covers
+/// windows that are still hidden.
+fn forward_theme_changes(window: &tauri::WebviewWindow) {
+    let window_handle = window.clone();
+    window.on_window_event(move |event| {
+        if let tauri::WindowEvent::ThemeChanged(theme) = event {
+            if let Err(error) =
+                window_handle.emit_to(window_handle.label(), "theme://changed", theme_name(*theme))
+            {
+                log::warn!("Failed to emit theme change event: {}", error);
+            }
+        }
+    });
+}
+
+/// Deliver deep-link URLs to the main window as `deep-link://received`, or
+/// buffer them until the frontend has drained the startup queue so nothing
+/// emitted before `DeepLinkHandler` mounts is dropped. `command-bar/<verb>`
+/// links are run directly instead. OAuth callbacks only go out as validated
+/// `auth://callback` events (queued the same way), never as raw URLs, so a
+/// token can't reach the frontend without its state check.
+fn forward_deep_links(app: &tauri::AppHandle, urls: Vec<String>) {
+    // Command bar control links are handled here and never reach the
+    // frontend, so they work without the main window.
+    let urls: Vec<String> = urls
+        .into_iter()
+        .filter(|url| match parse_command_bar_deep_link(url) {
+            Some(action) => {
+                #[cfg(desktop)]
+                run_command_bar_deep_link(app, action);
+                #[cfg(not(desktop))]
+                let _ = action;
+                false
+            }
+            None => true,
+        })
+        .collect();
+    let (auth_urls, urls): (Vec<String>, Vec<String>) = urls
+        .into_iter()
+        .partition(|url| parse_auth_callback(url).is_some());
+    let auth_callbacks = validated_auth_callbacks(app, &auth_urls);
+    if urls.is_empty() && auth_callbacks.is_empty() {
+        return;
+    }
+    {
+        let state = app.state::<DeepLinkState>();
+        let mut queue = lock_recovering(&state.queue, DEEP_LINK_STATE);
+        if !queue.frontend_ready || app.get_webview_window(MAIN_WINDOW_LABEL).is_none() {
+            queue.pending_urls.extend(urls);
+            queue.pending_auth_callbacks.extend(auth_callbacks);
+            let dropped = drop_oldest_pending(&mut queue.pending_urls)
+                + drop_oldest_pending(&mut queue.pending_auth_callbacks);
+            if dropped > 0 {
+                log::warn!("Dropping {} buffered deep links.", dropped);
+            }
+            return;
+        }
+    }
+
+    for callback in auth_callbacks {
+        if let Err(error) = app.emit_to(MAIN_WINDOW_LABEL, "auth://callback", callback) {
+            log::warn!("Failed to emit auth callback event: {}", error);
+        }
+    }
+    if urls.is_empty() {
+        return;
+    }
+    if let Err(error) = app.emit_to(MAIN_WINDOW_LABEL, "deep-link://received", urls) {
+        log::warn!("Failed to emit deep link event: {}", error);
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXIsProcessTrustedWithOptions(options: *const std::ffi::c_void) -> bool;
+}
+
+/// Whether Kompose has Accessibility permission. With `prompt`, macOS shows
+/// its "grant access" dialog if the user hasn't answered it yet.
+#[cfg(target_os = "macos")]
+fn is_accessibility_trusted(prompt: bool) -> bool {
+    use objc::runtime::{Object, BOOL, NO, YES};
+    use objc::{class, msg_send, sel, sel_impl};
+    unsafe {
+        // Value of the `kAXTrustedCheckOptionPrompt` CFString constant.
+        let key: *mut Object = msg_send![
+            class!(NSString),
+            stringWithUTF8String: c"AXTrustedCheckOptionPrompt".as_ptr()
+        ];
+        let prompt: BOOL = if prompt { YES } else { NO };
+        let value: *mut Object = msg_send![class!(NSNumber), numberWithBool: prompt];
+        let options: *mut Object =
+            msg_send![class!(NSDictionary), dictionaryWithObject: value forKey: key];
+        AXIsProcessTrustedWithOptions(options as *const std::ffi::c_void)
+    }
+}
+
+/// Returns the PID of the currently frontmost (active) application.
+#[cfg(target_os = "macos")]
+fn get_frontmost_app_pid() -> i32 {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    unsafe {
+        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: *mut Object = msg_send![workspace, frontmostApplication];
+        if app.is_null() {
+            return -1;
+        }
+        msg_send![app, processIdentifier]
+    }
+}
+
+/// Copies an `NSString` into a Rust `String`.
+#[cfg(target_os = "macos")]
+unsafe fn nsstring_to_string(string: *mut objc::runtime::Object) -> Option<String> {
+    use objc::{msg_send, sel, sel_impl};
+    if string.is_null() {
+        return None;
+    }
+    let utf8: *const std::os::raw::c_char = msg_send![string, UTF8String];
+    if utf8.is_null() {
+        return None;
+    }
+    Some(
+        std::ffi::CStr::from_ptr(utf8)
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+/// Returns the localized name of the currently frontmost application.
+#[cfg(target_os = "macos")]
+fn get_frontmost_app_name() -> Option<String> {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    unsafe {
+        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: *mut Object = msg_send![workspace, frontmostApplication];
+        if app.is_null() {
+            return None;
+        }
+        let name: *mut Object = msg_send![app, localizedName];
+        nsstring_to_string(name)
+    }
+}
+
+/// Regular apps from `NSWorkspace.runningApplications`, excluding Kompose
+/// and background/accessory processes.
+#[cfg(target_os = "macos")]
+fn get_running_apps() -> Vec<RunningApp> {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    // NSApplicationActivationPolicyRegular
+    const ACTIVATION_POLICY_REGULAR: isize = 0;
+    let our_pid = std::process::id() as i32;
+    let mut running_apps = Vec::new();
+    unsafe {
+        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let apps: *mut Object = msg_send![workspace, runningApplications];
+        if apps.is_null() {
+            return running_apps;
+        }
+        let count: usize = msg_send![apps, count];
+        for index in 0..count {
+            let app: *mut Object = msg_send![apps, objectAtIndex: index];
+            let policy: isize = msg_send![app, activationPolicy];
+            let pid: i32 = msg_send![app, processIdentifier];
+            if policy != ACTIVATION_POLICY_REGULAR || pid == our_pid {
+                continue;
+            }
+            let name: *mut Object = msg_send![app, localizedName];
+            let Some(name) = nsstring_to_string(name) else {
+                continue;
+            };
+            let bundle_id: *mut Object = msg_send![app, bundleIdentifier];
+            running_apps.push(RunningApp {
+                name,
+                bundle_id: nsstring_to_string(bundle_id),
+                pid,
+            });
+        }
+    }
+    running_apps
+}
+
+/// Brings the app with `pid` to the front. Returns false if it has exited.
+#[cfg(target_os = "macos")]
+fn activate_app_by_pid(pid: i32) -> bool {
+    use objc::runtime::{Object, BOOL, NO};
+    use objc::{class, msg_send, sel, sel_impl};
+    // NSApplicationActivateIgnoringOtherApps
+    const ACTIVATE_IGNORING_OTHER_APPS: usize = 1 << 1;
+    unsafe {
+        let app: *mut Object = msg_send![
+            class!(NSRunningApplication),
+            runningApplicationWithProcessIdentifier: pid
+        ];
+        if app.is_null() {
+            return false;
+        }
+        let activated: BOOL = msg_send![app, activateWithOptions: ACTIVATE_IGNORING_OTHER_APPS];
+        activated != NO
+    }
+}
+
+/// Whether the app with `pid` is still running (and not shutting down).
+#[cfg(target_os = "macos")]
+fn is_app_running(pid: i32) -> bool {
+    use objc::runtime::{Object, BOOL, NO};
+    use objc::{class, msg_send, sel, sel_impl};
+    unsafe {
+        let app: *mut Object = msg_send![
+            class!(NSRunningApplication),
+            runningApplicationWithProcessIdentifier: pid
+        ];
+        if app.is_null() {
+            return false;
+        }
+        let terminated: BOOL = msg_send![app, isTerminated];
+        terminated == NO
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) -> *const std::ffi::c_void;
+    fn CGEventCreateKeyboardEvent(
+        source: *const std::ffi::c_void,
+        virtual_key: u16,
+        key_down: bool,
+    ) -> *mut std::ffi::c_void;
+    fn CGEventSetFlags(event: *mut std::ffi::c_void, flags: u64);
+    fn CGEventPost(tap: u32, event: *mut std::ffi::c_void);
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFRelease(cf: *const std::ffi::c_void);
+}
+
+/// macOS virtual key code (`kVK_*`) for `code`, for the keys
+/// `send_keystroke_to_previous_app` supports.
+#[cfg(target_os = "macos")]
+fn macos_virtual_key(code: Code) -> Option<u16> {
+    let key = match code {
+        Code::KeyA => 0x00,
+        Code::KeyS => 0x01,
+        Code::KeyD => 0x02,
+        Code::KeyF => 0x03,
+        Code::KeyH => 0x04,
+        Code::KeyG => 0x05,
+        Code::KeyZ => 0x06,
+        Code::KeyX => 0x07,
+        Code::KeyC => 0x08,
+        Code::KeyV => 0x09,
+        Code::KeyB => 0x0B,
+        Code::KeyQ => 0x0C,
+        Code::KeyW => 0x0D,
+        Code::KeyE => 0x0E,
+        Code::KeyR => 0x0F,
+        Code::KeyY => 0x10,
+        Code::KeyT => 0x11,
+        Code::Digit1 => 0x12,
+        Code::Digit2 => 0x13,
+        Code::Digit3 => 0x14,
+        Code::Digit4 => 0x15,
+        Code::Digit6 => 0x16,
+        Code::Digit5 => 0x17,
+        Code::Equal => 0x18,
+        Code::Digit9 => 0x19,
+        Code::Digit7 => 0x1A,
+        Code::Minus => 0x1B,
+        Code::Digit8 => 0x1C,
+        Code::Digit0 => 0x1D,
+        Code::BracketRight => 0x1E,
+        Code::KeyO => 0x1F,
+        Code::KeyU => 0x20,
+        Code::BracketLeft => 0x21,
+        Code::KeyI => 0x22,
+        Code::KeyP => 0x23,
+        Code::Enter => 0x24,
+        Code::KeyL => 0x25,
+        Code::KeyJ => 0x26,
+        Code::Quote => 0x27,
+        Code::KeyK => 0x28,
+        Code::Semicolon => 0x29,
+        Code::Backslash => 0x2A,
+        Code::Comma => 0x2B,
+        Code::Slash => 0x2C,
+        Code::KeyN => 0x2D,
+        Code::KeyM => 0x2E,
+        Code::Period => 0x2F,
+        Code::Tab => 0x30,
+        Code::Space => 0x31,
+        Code::Backquote => 0x32,
+        Code::Backspace => 0x33,
+        Code::Escape => 0x35,
+        Code::F5 => 0x60,
+        Code::F6 => 0x61,
+        Code::F7 => 0x62,
+        Code::F3 => 0x63,
+        Code::F8 => 0x64,
+        Code::F9 => 0x65,
+        Code::F11 => 0x67,
+        Code::F10 => 0x6D,
+        Code::F12 => 0x6F,
+        Code::Home => 0x73,
+        Code::PageUp => 0x74,
+        Code::Delete => 0x75,
+        Code::F4 => 0x76,
+        Code::End => 0x77,
+        Code::F2 => 0x78,
+        Code::PageDown => 0x79,
+        Code::F1 => 0x7A,
+        Code::ArrowLeft => 0x7B,
+        Code::ArrowRight => 0x7C,
+        Code::ArrowDown => 0x7D,
+        Code::ArrowUp => 0x7E,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Post a key-down/key-up pair for `virtual_key` with `mods` held to the HID
+/// event tap, so it reaches whichever app is frontmost.
+#[cfg(target_os = "macos")]
+fn post_keystroke(virtual_key: u16, mods: Modifiers) {
+    // kCGHIDEventTap
+    const HID_EVENT_TAP: u32 = 0;
+    let mut flags: u64 = 0;
+    for (modifier, flag) in [
+        (Modifiers::SHIFT, 1_u64 << 17), // kCGEventFlagMaskShift
+        (Modifiers::CONTROL, 1 << 18),   // kCGEventFlagMaskControl
+        (Modifiers::ALT, 1 << 19),       // kCGEventFlagMaskAlternate
+        (Modifiers::SUPER, 1 << 20),     // kCGEventFlagMaskCommand
+    ] {
+        if mods.contains(modifier) {
+            flags |= flag;
+        }
+    }
+    unsafe {
+        for key_down in [true, false] {
+            let event = CGEventCreateKeyboardEvent(std::ptr::null(), virtual_key, key_down);
+            if event.is_null() {
+                log::warn!("Failed to create keyboard event.");
+                return;
+            }
+            CGEventSetFlags(event, flags);
+            CGEventPost(HID_EVENT_TAP, event);
+            CFRelease(event);
+        }
+    }
+}
+
+/// Whether the app with `pid` has a normal-level window on screen. False when
+/// all of its windows are minimized to the Dock. Errs towards true when the
+/// window list is unavailable so callers keep their default behavior.
+#[cfg(target_os = "macos")]
+fn app_has_on_screen_windows(pid: i32) -> bool {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    // kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements
+    const WINDOW_LIST_OPTIONS: u32 = (1 << 0) | (1 << 4);
+    unsafe {
+        let windows = CGWindowListCopyWindowInfo(WINDOW_LIST_OPTIONS, 0) as *mut Object;
+        if windows.is_null() {
+            return true;
+        }
+        // Values of the `kCGWindowOwnerPID` and `kCGWindowLayer` CFString constants.
+        let owner_key: *mut Object = msg_send![
+            class!(NSString),
+            stringWithUTF8String: c"kCGWindowOwnerPID".as_ptr()
+        ];
+        let layer_key: *mut Object = msg_send![
+            class!(NSString),
+            stringWithUTF8String: c"kCGWindowLayer".as_ptr()
+        ];
+
+        let count: usize = msg_send![windows, count];
+        let mut found = false;
+        for index in 0..count {
+            let info: *mut Object = msg_send![windows, objectAtIndex: index];
+            let owner: *mut Object = msg_send![info, objectForKey: owner_key];
+            let layer: *mut Object = msg_send![info, objectForKey: layer_key];
+            if owner.is_null() || layer.is_null() {
+                continue;
+            }
+            let owner_pid: i32 = msg_send![owner, intValue];
+            let layer: isize = msg_send![layer, integerValue];
+            if owner_pid == pid && layer == 0 {
+                found = true;
+                break;
+            }
+        }
+        let _: () = msg_send![windows, release];
+        found
+    }
+}
+
+/// Hides the entire application and activates the previously active app.
+/// This is the macOS equivalent of Cmd+H and is atomic — no intermediate
+/// state where the main window is visible, so there's no flicker.
+#[cfg(target_os = "macos")]
+fn hide_app() {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    unsafe {
+        let ns_app: *mut Object = msg_send![class!(NSApplication), sharedApplication];
+        let _: () = msg_send![ns_app, hide: std::ptr::null::<Object>()];
+    }
+}
+
+/// Undo `hide_app()` and make Kompose the active app again. Harmless when
+/// the app isn't hidden.
+#[cfg(target_os = "macos")]
+fn unhide_app() {
+    use objc::runtime::{Object, YES};
+    use objc::{class, msg_send, sel, sel_impl};
+    unsafe {
+        let ns_app: *mut Object = msg_send![class!(NSApplication), sharedApplication];
+        let _: () = msg_send![ns_app, unhide: std::ptr::null::<Object>()];
+        let _: () = msg_send![ns_app, activateIgnoringOtherApps: YES];
+    }
+}
+
+/// Returns the raw HWND of the current foreground window (0 if none).
+#[cfg(target_os = "windows")]
+fn get_foreground_window() -> isize {
+    use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+    unsafe { GetForegroundWindow().0 as isize }
+}
+
+/// Whether `hwnd` belongs to this process, i.e. reactivating it would just
+/// hand focus back to Kompose.
+#[cfg(target_os = "windows")]
+fn is_own_window(hwnd: isize) -> bool {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+    let mut pid = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(HWND(hwnd as *mut _), Some(&mut pid));
+    }
+    pid == std::process::id()
+}
+
+/// Moves `hwnd` to the foreground. Windows only honors this while our
+/// process owns the foreground, which holds while the command bar is focused.
+#[cfg(target_os = "windows")]
+fn activate_window(hwnd: isize) -> bool {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::{IsWindow, SetForegroundWindow};
+    let hwnd = HWND(hwnd as *mut _);
+    unsafe { IsWindow(Some(hwnd)).as_bool() && SetForegroundWindow(hwnd).as_bool() }
+}
+
+/// Windows counterpart of `hide_app()`: hand the foreground to
+/// `previous_hwnd`, then hide the command bar and minimize the other visible
+/// Kompose windows without activating them, so none of ours flashes on the
+/// way out. If the previous window can't be activated the other windows are
+/// left alone. Returns whether activation succeeded.
+#[cfg(target_os = "windows")]
+fn hide_app_windows(app: &tauri::AppHandle, previous_hwnd: isize) -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{
+        IsWindowVisible, ShowWindow, SW_HIDE, SW_SHOWMINNOACTIVE,
+    };
+    let activated = activate_window(previous_hwnd);
+    for (label, window) in app.webview_windows() {
+        let command = if label == COMMAND_BAR_WINDOW_LABEL {
+            SW_HIDE
+        } else if activated {
+            SW_SHOWMINNOACTIVE
+        } else {
+            continue;
+        };
+        let Ok(hwnd) = window.hwnd() else {
+            continue;
+        };
+        unsafe {
+            if IsWindowVisible(hwnd).as_bool() {
+                let _ = ShowWindow(hwnd, command);
+            }
+        }
+    }
+    activated
+}
+
+/// Wayland compositors don't let clients query or change the active window,
+/// so focus restore is X11 only.
+#[cfg(target_os = "linux")]
+fn is_wayland_session() -> bool {
+    std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+        || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+#[cfg(target_os = "linux")]
+fn x11_atom(conn: &impl x11rb::connection::Connection, name: &[u8]) -> Option<u32> {
+    use x11rb::protocol::xproto::ConnectionExt;
+    Some(conn.intern_atom(false, name).ok()?.reply().ok()?.atom)
+}
+
+/// Returns the X11 window named by the root window's `_NET_ACTIVE_WINDOW`
+/// (0 if none or no X server is reachable).
+#[cfg(target_os = "linux")]
+fn get_active_x11_window() -> u32 {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+    let query = || -> Option<u32> {
+        let (conn, screen_num) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots.get(screen_num)?.root;
+        let active_window = x11_atom(&conn, b"_NET_ACTIVE_WINDOW")?;
+        let reply = conn
+            .get_property(false, root, active_window, AtomEnum::WINDOW, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let value = reply.value32()?.next();
+        value
+    };
+    query().unwrap_or(0)
+}
+
+/// Whether `window` belongs to this process according to `_NET_WM_PID`.
+#[cfg(target_os = "linux")]
+fn is_own_x11_window(window: u32) -> bool {
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+    let query = || -> Option<u32> {
+        let (conn, _) = x11rb::connect(None).ok()?;
+        let wm_pid = x11_atom(&conn, b"_NET_WM_PID")?;
+        let reply = conn
+            .get_property(false, window, wm_pid, AtomEnum::CARDINAL, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let value = reply.value32()?.next();
+        value
+    };
+    query() == Some(std::process::id())
+}
+
+/// Ask the window manager to activate `window` via a `_NET_ACTIVE_WINDOW`
+/// client message, flagged as coming from a pager so focus-stealing
+/// prevention lets it through.
+#[cfg(target_os = "linux")]
+fn activate_x11_window(window: u32) -> bool {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{ClientMessageEvent, ConnectionExt, EventMask};
+    const SOURCE_INDICATION_PAGER: u32 = 2;
+    let activate = || -> Option<()> {
+        let (conn, screen_num) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots.get(screen_num)?.root;
+        let active_window = x11_atom(&conn, b"_NET_ACTIVE_WINDOW")?;
+        let event = ClientMessageEvent::new(
+            32,
+            window,
+            active_window,
+            [SOURCE_INDICATION_PAGER, x11rb::CURRENT_TIME, 0, 0, 0],
+        );
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )
+        .ok()?;
+        conn.flush().ok()
+    };
+    activate().is_some()
+}
+
+/// Switch between a regular Dock app and an accessory (launcher-style, no
+/// Dock icon or app menu) app. `[NSApp hide:]` still reactivates the
+/// previous app in accessory mode, so `dismiss_command_bar` is unaffected.
+#[cfg(target_os = "macos")]
+fn set_dock_icon_visible_native(visible: bool) {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    // NSApplicationActivationPolicyRegular = 0, NSApplicationActivationPolicyAccessory = 1.
+    let policy: isize = if visible { 0 } else { 1 };
+    unsafe {
+        let ns_app: *mut Object = msg_send![class!(NSApplication), sharedApplication];
+        let _: bool = msg_send![ns_app, setActivationPolicy: policy];
+    }
+}
+
+/// Let the command bar float over fullscreen apps: join every Space
+/// (including fullscreen ones) as an auxiliary window and sit above the
+/// menu bar, so opening it doesn't trigger a Space switch.
+#[cfg(target_os = "macos")]
+fn configure_command_bar_for_fullscreen_spaces(command_bar_window: &tauri::WebviewWindow) {
+    use objc::runtime::Object;
+    use objc::{msg_send, sel, sel_impl};
+    const NS_WINDOW_COLLECTION_BEHAVIOR_CAN_JOIN_ALL_SPACES: usize = 1 << 0;
+    const NS_WINDOW_COLLECTION_BEHAVIOR_FULL_SCREEN_AUXILIARY: usize = 1 << 8;
+    const NS_MAIN_MENU_WINDOW_LEVEL: isize = 24;
+
+    let ns_window = match command_bar_window.ns_window() {
+        Ok(ns_window) => ns_window as *mut Object,
+        Err(error) => {
+            log::warn!("Failed to access command bar NSWindow: {}", error);
+            return;
+        }
+    };
+    unsafe {
+        let behavior: usize = msg_send![ns_window, collectionBehavior];
+        let behavior = behavior
+            | NS_WINDOW_COLLECTION_BEHAVIOR_CAN_JOIN_ALL_SPACES
+            | NS_WINDOW_COLLECTION_BEHAVIOR_FULL_SCREEN_AUXILIARY;
+        let _: () = msg_send![ns_window, setCollectionBehavior: behavior];
+        let _: () = msg_send![ns_window, setLevel: NS_MAIN_MENU_WINDOW_LEVEL + 1];
+    }
+}
+
+#[cfg(desktop)]
+fn primary_modifier() -> Modifiers {
+    if cfg!(target_os = "macos") {
+        Modifiers::SUPER
+    } else {
+        Modifiers::CONTROL
+    }
+}
+
+/// Platform display label and glyph for a single modifier flag. macOS uses
+/// the menu-bar glyphs; other platforms spell the key out.
+#[cfg(desktop)]
+fn modifier_display(modifier: Modifiers) -> (&'static str, &'static str) {
+    let is_macos = cfg!(target_os = "macos");
+    if modifier == Modifiers::SUPER {
+        if is_macos {
+            ("Cmd", "⌘")
+        } else {
+            ("Win", "Win")
+        }
+    } else if modifier == Modifiers::CONTROL {
+        if is_macos {
+            ("Ctrl", "⌃")
+        } else {
+            ("Ctrl", "Ctrl")
+        }
+    } else if modifier == Modifiers::ALT {
+        if is_macos {
+            ("Option", "⌥")
+        } else {
+            ("Alt", "Alt")
+        }
+    } else if modifier == Modifiers::SHIFT {
+        if is_macos {
+            ("Shift", "⇧")
+        } else {
+            ("Shift", "Shift")
+        }
+    } else {
+        ("", "")
+    }
+}
+
+#[cfg(desktop)]
+fn primary_modifier_info() -> PrimaryModifierInfo {
+    let primary = primary_modifier();
+    let (label, glyph) = modifier_display(primary);
+    PrimaryModifierInfo {
+        name: if primary == Modifiers::SUPER {
+            "super"
+        } else {
+            "ctrl"
+        },
+        label,
+        glyph,
+    }
+}
+
+/// Display name for a key code, e.g. `KeyK` -> `K`, `ArrowUp` -> `Up`.
+#[cfg(desktop)]
+fn key_display(code: Code) -> String {
+    let name = code.to_string();
+    for prefix in ["Key", "Digit", "Arrow"] {
+        if let Some(stripped) = name.strip_prefix(prefix) {
+            return stripped.to_string();
+        }
+    }
+    name
+}
+
+/// Platform display string for a shortcut: `⇧⌘K` on macOS, `Ctrl+Shift+K`
+/// elsewhere. Both follow the Control, Alt/Option, Shift, Cmd/Win ordering.
+#[cfg(desktop)]
+fn shortcut_display(shortcut: &Shortcut) -> String {
+    let is_macos = cfg!(target_os = "macos");
+    let ordered_modifiers = [
+        Modifiers::CONTROL,
+        Modifiers::ALT,
+        Modifiers::SHIFT,
+        Modifiers::SUPER,
+    ];
+
+    let mut parts: Vec<String> = ordered_modifiers
+        .into_iter()
+        .filter(|modifier| shortcut.mods.contains(*modifier))
+        .map(|modifier| modifier_display(modifier).1.to_string())
+        .collect();
+    parts.push(key_display(shortcut.key));
+
+    parts.join(if is_macos { "" } else { "+" })
+}
+
+#[cfg(desktop)]
+fn shortcut_for_preset(preset_id: &str) -> Option<Shortcut> {
+    let primary = primary_modifier();
+
+    let shortcut = match preset_id {
+        "cmd_or_ctrl_shift_k" => Shortcut::new(Some(primary | Modifiers::SHIFT), Code::KeyK),
+        "ctrl_space" => Shortcut::new(Some(Modifiers::CONTROL), Code::Space),
+        "alt_space" => Shortcut::new(Some(Modifiers::ALT), Code::Space),
+        _ => {
+            let binding = preset_id.strip_prefix(CUSTOM_SHORTCUT_PRESET_PREFIX)?;
+            return parse_shortcut_string(binding);
+        }
+    };
+
+    Some(shortcut)
+}
+
+/// Parse a modifier name, accepting the labels shortcut UIs display (`Cmd`,
+/// `Opt`, `Control`, ...) as aliases.
+#[cfg(desktop)]
+fn parse_modifier_token(token: &str) -> Option<Modifiers> {
+    match token.trim().to_ascii_lowercase().as_str() {
+        "super" | "cmd" | "command" | "meta" => Some(Modifiers::SUPER),
+        "ctrl" | "control" => Some(Modifiers::CONTROL),
+        "alt" | "opt" | "option" => Some(Modifiers::ALT),
+        "shift" => Some(Modifiers::SHIFT),
+        _ => None,
+    }
+}
+
+/// Parse a `Code` name (`KeyJ`, `Space`, `F5`), also accepting bare letters
+/// and digits (`J`, `5`).
+#[cfg(desktop)]
+fn parse_key_code(key: &str) -> Option<Code> {
+    let key = key.trim();
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphabetic() {
+            return format!("Key{}", c.to_ascii_uppercase()).parse().ok();
+        }
+        if c.is_ascii_digit() {
+            return format!("Digit{}", c).parse().ok();
+        }
+    }
+    key.parse().ok()
+}
+
+/// Build a user-defined shortcut, rejecting bindings that would hijack
+/// normal typing (no modifier, or Shift alone).
+#[cfg(desktop)]
+fn custom_shortcut<S: AsRef<str>>(modifiers: &[S], key: &str) -> Result<Shortcut, String> {
+    let mut mods = Modifiers::empty();
+    for token in modifiers {
+        let token = token.as_ref();
+        mods |= parse_modifier_token(token).ok_or_else(|| {
+            format!(
+                "Unknown shortcut modifier '{}'. Expected super, ctrl, alt, or shift.",
+                token
+            )
+        })?;
+    }
+    let code = parse_key_code(key).ok_or_else(|| format!("Unknown shortcut key '{}'.", key))?;
+
+    if mods.is_empty() || mods == Modifiers::SHIFT {
+        return Err(
+            "Custom shortcuts need at least one of super, ctrl, or alt so they don't capture normal typing."
+                .to_string(),
+        );
+    }
+
+    Ok(Shortcut::new(Some(mods), code))
+}
+
+/// Parse a `+`-separated combo such as `"Cmd+Shift+K"`: any number of
+/// modifiers, in any order, and exactly one key. Errors name the token that
+/// couldn't be understood.
+#[cfg(desktop)]
+fn shortcut_from_string(combo: &str) -> Result<Shortcut, String> {
+    let mut modifiers: Vec<&str> = Vec::new();
+    let mut key: Option<&str> = None;
+    for token in combo.split('+').map(str::trim) {
+        if token.is_empty() {
+            return Err(format!("Shortcut '{}' has an empty key.", combo));
+        }
+        if parse_modifier_token(token).is_some() {
+            modifiers.push(token);
+            continue;
+        }
+        if parse_key_code(token).is_none() {
+            return Err(format!("Unknown shortcut key or modifier '{}'.", token));
+        }
+        if key.replace(token).is_some() {
+            return Err(format!("Shortcut '{}' has more than one key.", combo));
+        }
+    }
+    let key = key.ok_or_else(|| format!("Shortcut '{}' doesn't include a key.", combo))?;
+    custom_shortcut(&modifiers, key)
+}
+
+/// `shortcut_from_string` without the error detail, e.g. for stored ids.
+#[cfg(desktop)]
+fn parse_shortcut_string(combo: &str) -> Option<Shortcut> {
+    shortcut_from_string(combo).ok()
+}
+
+/// Canonical `custom:` preset id for a shortcut, with modifiers in a fixed
+/// order so the same binding always maps to the same id.
+#[cfg(desktop)]
+fn custom_shortcut_preset_id(shortcut: &Shortcut) -> String {
+    let mut tokens: Vec<String> = [
+        (Modifiers::CONTROL, "ctrl"),
+        (Modifiers::ALT, "alt"),
+        (Modifiers::SHIFT, "shift"),
+        (Modifiers::SUPER, "super"),
+    ]
+    .into_iter()
+    .filter(|(modifier, _)| shortcut.mods.contains(*modifier))
+    .map(|(_, token)| token.to_string())
+    .collect();
+    tokens.push(shortcut.key.to_string());
+    format!("{}{}", CUSTOM_SHORTCUT_PRESET_PREFIX, tokens.join("+"))
+}
+
+/// Preset persisted by `set_command_bar_shortcut_preset`, falling back to the
+/// default when it's missing (e.g. a fresh install) or, with a warning, when
+/// it no longer maps to a known preset.
+#[cfg(desktop)]
+fn stored_shortcut_preset(app: &tauri::AppHandle) -> String {
+    let Some(stored) = settings_store(app)
+        .ok()
+        .and_then(|store| store.get(SHORTCUT_PRESET_STORE_KEY))
+    else {
+        return DEFAULT_SHORTCUT_PRESET_ID.to_string();
+    };
+    match stored.as_str() {
+        Some(preset_id) if shortcut_for_preset(preset_id).is_some() => preset_id.to_string(),
+        _ => {
+            log::warn!(
+                "Stored command bar shortcut preset {} is not supported; using '{}'.",
+                stored,
+                DEFAULT_SHORTCUT_PRESET_ID
+            );
+            DEFAULT_SHORTCUT_PRESET_ID.to_string()
+        }
+    }
+}
+
+/// Presets persisted by the shortcut commands, falling back to the single
+/// preset stored before multiple bindings were supported.
+#[cfg(desktop)]
+fn stored_shortcut_presets(app: &tauri::AppHandle) -> Vec<String> {
+    let stored = settings_store(app)
+        .ok()
+        .and_then(|store| store.get(SHORTCUT_PRESET_IDS_STORE_KEY));
+    let mut preset_ids: Vec<String> = Vec::new();
+    if let Some(serde_json::Value::Array(values)) = stored {
+        for preset_id in values.iter().filter_map(|value| value.as_str()) {
+            if shortcut_for_preset(preset_id).is_none() {
+                log::warn!(
+                    "Stored command bar shortcut preset '{}' is not supported; skipping it.",
+                    preset_id
+                );
+            } else if !preset_ids.iter().any(|id| id == preset_id) {
+                preset_ids.push(preset_id.to_string());
+            }
+        }
+    }
+    if preset_ids.is_empty() {
+        preset_ids.push(stored_shortcut_preset(app));
+    }
+    preset_ids
+}
+
+/// Trimmed `preset_id` if it maps to a shortcut the command bar may use.
+#[cfg(desktop)]
+fn validate_shortcut_preset(preset_id: &str) -> Result<String, KomposeError> {
+    let preset_id = preset_id.trim();
+    let shortcut = shortcut_for_preset(preset_id).ok_or_else(|| {
+        KomposeError::ShortcutUnsupported(format!(
+            "Unsupported command bar shortcut preset '{}'.",
+            preset_id
+        ))
+    })?;
+    if shortcut == main_window_shortcut() {
+        return Err(KomposeError::ShortcutUnsupported(format!(
+            "{} is reserved for showing and hiding the main window.",
+            shortcut_display(&shortcut)
+        )));
+    }
+    Ok(preset_id.to_string())
+}
+
+/// Whether the command bar shortcut is enabled; on unless the user suspended it.
+#[cfg(desktop)]
+fn stored_shortcut_enabled(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(SHORTCUT_ENABLED_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true)
+}
+
+#[cfg(desktop)]
+fn register_shortcut_preset(app: &tauri::AppHandle, preset_id: &str) -> Result<(), KomposeError> {
+    let shortcut = shortcut_for_preset(preset_id).ok_or_else(|| {
+        KomposeError::ShortcutUnsupported(format!(
+            "Unsupported command bar shortcut preset '{}'.",
+            preset_id
+        ))
+    })?;
+    app.global_shortcut().register(shortcut).map_err(|error| {
+        KomposeError::ShortcutRegistrationFailed(format!(
+            "Failed to register shortcut preset '{}': {}",
+            preset_id, error
+        ))
+    })?;
+    bind_shortcut_action(app, shortcut, ShortcutAction::ToggleCommandBar);
+    Ok(())
+}
+
+/// Register every preset, carrying on past failures so one conflicting
+/// binding doesn't take the others down. Returns the first error.
+#[cfg(desktop)]
+fn register_shortcut_presets(
+    app: &tauri::AppHandle,
+    preset_ids: &[String],
+) -> Result<(), KomposeError> {
+    let mut first_error = None;
+    for preset_id in preset_ids {
+        if let Err(error) = register_shortcut_preset(app, preset_id) {
+            first_error.get_or_insert(error);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+#[cfg(desktop)]
+fn record_shortcut_registration(
+    state: &CommandBarShortcutState,
+    result: &Result<(), KomposeError>,
+) {
+    *state.lock_registration() = ShortcutRegistration {
+        registered: result.is_ok(),
+        error: result.as_ref().err().map(ToString::to_string),
+    };
+}
+
+/// Route presses of `shortcut` to `action` in the global shortcut handler.
+#[cfg(desktop)]
+fn bind_shortcut_action(app: &tauri::AppHandle, shortcut: Shortcut, action: ShortcutAction) {
+    let state = app.state::<CommandBarShortcutState>();
+    lock_recovering(&state.shortcut_actions, SHORTCUT_ACTIONS_STATE).insert(shortcut, action);
+}
+
+#[cfg(desktop)]
+fn unbind_shortcut_action(app: &tauri::AppHandle, shortcut: &Shortcut) {
+    let state = app.state::<CommandBarShortcutState>();
+    lock_recovering(&state.shortcut_actions, SHORTCUT_ACTIONS_STATE).remove(shortcut);
+}
+
+#[cfg(desktop)]
+fn shortcut_action(app: &tauri::AppHandle, shortcut: &Shortcut) -> Option<ShortcutAction> {
+    let state = app.state::<CommandBarShortcutState>();
+    let actions = lock_recovering(&state.shortcut_actions, SHORTCUT_ACTIONS_STATE);
+    actions.get(shortcut).copied()
+}
+
+#[cfg(desktop)]
+fn unregister_shortcut_preset(app: &tauri::AppHandle, preset_id: &str) {
+    let Some(shortcut) = shortcut_for_preset(preset_id) else {
+        return;
+    };
+    unbind_shortcut_action(app, &shortcut);
+    if let Err(error) = app.global_shortcut().unregister(shortcut) {
+        log::warn!(
+            "Failed to unregister shortcut preset '{}': {}",
+            preset_id,
+            error
+        );
+    }
+}
+
+/// Record that the command bar was shown or hidden (for idle teardown) and
+/// tell every window, so the command bar route can reset its input on open
+/// without relying on focus events.
+#[cfg(desktop)]
+fn command_bar_visibility_changed(app: &tauri::AppHandle, visible: bool) {
+    let state = app.state::<CommandBarShortcutState>();
+    *lock_recovering(&state.hidden_since, HIDDEN_SINCE_STATE) =
+        if visible { None } else { Some(Instant::now()) };
+    if !visible {
+        *lock_recovering(&state.sticky, STICKY_STATE) = false;
+    }
+    if let Err(error) = app.emit("command-bar://visibility", CommandBarVisibility { visible }) {
+        log::warn!("Failed to emit command bar visibility event: {}", error);
+    }
+}
+
+/// Build the command bar window, or return it if it already exists. Created
+/// lazily on first open rather than at startup so users who never open it
+/// don't pay for a second webview, and so a webview that fails to build
+/// can't take the main app down with it; failures are retried on the next
+/// open.
+#[cfg(desktop)]
+fn create_command_bar_window(app: &tauri::AppHandle) -> tauri::Result<tauri::WebviewWindow> {
+    if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        return Ok(command_bar_window);
+    }
+
+    let always_on_top = stored_command_bar_always_on_top(app);
+    let capture_excluded = stored_command_bar_capture_excluded(app);
+    let command_bar_window_builder = tauri::WebviewWindowBuilder::new(
+        app,
+        COMMAND_BAR_WINDOW_LABEL,
+        tauri::WebviewUrl::App(COMMAND_BAR_WINDOW_ROUTE.into()),
+    )
+    .title("Kompose Command Bar")
+    .visible(false)
+    .focused(false)
+    .resizable(false)
+    .decorations(false)
+    .always_on_top(always_on_top)
+    .content_protected(capture_excluded)
+    .skip_taskbar(true)
+    // Start at the remembered size so the window doesn't visibly jump when the
+    // page re-requests it on mount.
+    .inner_size(
+        stored_command_bar_width(app),
+        stored_command_bar_height(app),
+    )
+    // A freshly (re)created bar is shown before its page has loaded, so the
+    // focus-input event from the open is missed; send it again once loaded.
+    // A reload also drops the frontend's Escape handling until it reports
+    // ready again, so fall back to the native binding meanwhile.
+    .on_page_load(|command_bar_window, payload| match payload.event() {
+        tauri::webview::PageLoadEvent::Started => {
+            let state = command_bar_window.state::<CommandBarShortcutState>();
+            *lock_recovering(&state.frontend_ready, FRONTEND_READY_STATE) = false;
+            if command_bar_window.is_focused().unwrap_or(false) {
+                set_command_bar_escape_enabled(command_bar_window.app_handle(), true);
+            }
+        }
+        tauri::webview::PageLoadEvent::Finished => {
+            if command_bar_window.is_visible().unwrap_or(false) {
+                emit_command_bar_focus_input(&command_bar_window);
+            }
+        }
+    });
+
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    let command_bar_window_builder = command_bar_window_builder.transparent(true);
+
+    let state = app.state::<CommandBarShortcutState>();
+    let previously_failed = *lock_recovering(&state.creation_failed, CREATION_FAILED_STATE);
+    if previously_failed {
+        log::info!("Retrying command bar window creation after an earlier failure.");
+    }
+    let command_bar_window = match command_bar_window_builder.build() {
+        Ok(command_bar_window) => command_bar_window,
+        Err(error) => {
+            log::error!("Failed to create command bar window: {}", error);
+            *lock_recovering(&state.creation_failed, CREATION_FAILED_STATE) = true;
+            return Err(error);
+        }
+    };
+    *lock_recovering(&state.creation_failed, CREATION_FAILED_STATE) = false;
+
+    #[cfg(target_os = "macos")]
+    {
+        // Match the rounded command surface with a native macOS HUD material
+        // so the frameless popup keeps soft corners instead of a square shell.
+        if let Err(error) = apply_vibrancy(
+            &command_bar_window,
+            NSVisualEffectMaterial::HudWindow,
+            Some(NSVisualEffectState::Active),
+            Some(12.0),
+        ) {
+            log::warn!("Failed to apply command bar vibrancy: {}", error);
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    if always_on_top {
+        configure_command_bar_for_fullscreen_spaces(&command_bar_window);
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // Mica is Windows 11 only; fall back to acrylic on Windows 10 so the
+        // transparent popup still gets a blurred backdrop.
+        if apply_mica(&command_bar_window, None).is_err() {
+            if let Err(error) = apply_acrylic(&command_bar_window, Some((18, 18, 18, 125))) {
+                log::warn!("Failed to apply command bar backdrop: {}", error);
+            }
+        }
+    }
+
+    let opacity = stored_command_bar_opacity(app);
+    if opacity < COMMAND_BAR_MAX_OPACITY {
+        if let Err(error) = apply_command_bar_opacity(&command_bar_window, opacity) {
+            log::warn!("{}", error);
+        }
+    }
+
+    forward_theme_changes(&command_bar_window);
+
+    // Hide the popup when focus leaves the command bar window (e.g. user
+    // clicks another app). For programmatic Esc-dismiss the frontend calls
+    // the dismiss_command_bar command which activates the previous app first.
+    //
+    // Until the page reports ready, Escape is also bound natively while the
+    // command bar has focus, so the always-on-top bar can be dismissed even if
+    // its webview never loaded. Once ready the webview keeps Escape, which it
+    // uses for sub-view back navigation.
+    //
+    // Close requests (OS close, frontend `close()`) only hide the bar so it's
+    // always there for the next shortcut; intentional teardown goes through
+    // `destroy()`, which skips this event.
+    let window_handle = command_bar_window.clone();
+    command_bar_window.on_window_event(move |event| {
+        let app = window_handle.app_handle();
+        match event {
+            tauri::WindowEvent::Focused(true) => {
+                let state = window_handle.state::<CommandBarShortcutState>();
+                let frontend_ready = *lock_recovering(&state.frontend_ready, FRONTEND_READY_STATE);
+                if !frontend_ready {
+                    set_command_bar_escape_enabled(app, true);
+                }
+            }
+            tauri::WindowEvent::Focused(false) => {
+                set_command_bar_escape_enabled(app, false);
+                let state = window_handle.state::<CommandBarShortcutState>();
+                let recently_shown = lock_recovering(&state.last_shown_at, SHOWN_AT_STATE)
+                    .is_some_and(|shown_at| shown_at.elapsed() < COMMAND_BAR_FOCUS_LOSS_GRACE);
+                let sticky = *lock_recovering(&state.sticky, STICKY_STATE);
+                if recently_shown || sticky {
+                    return;
+                }
+                if window_handle.hide().is_ok() {
+                    command_bar_visibility_changed(app, false);
+                }
+            }
+            tauri::WindowEvent::CloseRequested { api, .. } => {
+                api.prevent_close();
+                if let Err(error) = hide_command_bar(app) {
+                    log::warn!("Failed to hide command bar on close: {}", error);
+                }
+            }
+            tauri::WindowEvent::Destroyed => {
+                let state = window_handle.state::<CommandBarShortcutState>();
+                *lock_recovering(&state.frontend_ready, FRONTEND_READY_STATE) = false;
+                set_command_bar_escape_enabled(app, false);
+            }
+            _ => {}
+        }
+    });
+
+    Ok(command_bar_window)
+}
+
+/// Destroy the command bar webview once it has been hidden for
+/// `COMMAND_BAR_IDLE_TEARDOWN_AFTER`. Checked periodically on a background
+/// thread; the next open recreates the window.
+#[cfg(desktop)]
+fn spawn_command_bar_idle_teardown(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(COMMAND_BAR_IDLE_CHECK_INTERVAL);
+
+        let state = app.state::<CommandBarShortcutState>();
+        let idle = lock_recovering(&state.hidden_since, HIDDEN_SINCE_STATE)
+            .is_some_and(|hidden_at| hidden_at.elapsed() >= COMMAND_BAR_IDLE_TEARDOWN_AFTER);
+        if !idle {
+            continue;
+        }
+
+        let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
+            continue;
+        };
+        if command_bar_window.is_visible().unwrap_or(true) {
+            continue;
+        }
+        match command_bar_window.destroy() {
+            Ok(()) => log::info!("Destroyed idle command bar window."),
+            Err(error) => log::warn!("Failed to destroy idle command bar window: {}", error),
+        }
+    });
+}
+
+/// Whether the user turned on presentation mode, which keeps the command bar
+/// from opening (e.g. while screen sharing).
+#[cfg(desktop)]
+fn stored_presentation_mode(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(PRESENTATION_MODE_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+/// Whether the command bar floats above other windows; on unless the user
+/// turned it off (e.g. for screen sharing).
+#[cfg(desktop)]
+fn stored_command_bar_always_on_top(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_ALWAYS_ON_TOP_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true)
+}
+
+/// Whether the command bar is hidden from screenshots and screen recordings;
+/// off unless the user turned it on.
+#[cfg(desktop)]
+fn stored_command_bar_capture_excluded(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_CAPTURE_EXCLUDED_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+/// Width last requested via `set_command_bar_width`, or the default.
+#[cfg(desktop)]
+fn stored_command_bar_width(app: &tauri::AppHandle) -> f64 {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_WIDTH_STORE_KEY))
+        .and_then(|value| value.as_f64())
+        .filter(|width| width.is_finite())
+        .map(|width| width.clamp(COMMAND_BAR_MIN_WIDTH, COMMAND_BAR_MAX_WIDTH))
+        .unwrap_or(COMMAND_BAR_DEFAULT_WIDTH)
+}
+
+/// Height last requested via `set_command_bar_height`, or the default.
+#[cfg(desktop)]
+fn stored_command_bar_height(app: &tauri::AppHandle) -> f64 {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_HEIGHT_STORE_KEY))
+        .and_then(|value| value.as_f64())
+        .filter(|height| height.is_finite())
+        .map(|height| height.clamp(COMMAND_BAR_MIN_HEIGHT, COMMAND_BAR_MAX_HEIGHT))
+        .unwrap_or(COMMAND_BAR_DEFAULT_HEIGHT)
+}
+
+/// Opacity last set via `set_command_bar_opacity`, or fully opaque.
+#[cfg(desktop)]
+fn stored_command_bar_opacity(app: &tauri::AppHandle) -> f64 {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_OPACITY_STORE_KEY))
+        .and_then(|value| value.as_f64())
+        .filter(|opacity| opacity.is_finite())
+        .map(|opacity| opacity.clamp(COMMAND_BAR_MIN_OPACITY, COMMAND_BAR_MAX_OPACITY))
+        .unwrap_or(COMMAND_BAR_MAX_OPACITY)
+}
+
+/// Fade the whole command bar window, content included: `alphaValue` on
+/// macOS, layered-window alpha on Windows. Not supported on Linux.
+#[cfg(desktop)]
+fn apply_command_bar_opacity(
+    command_bar_window: &tauri::WebviewWindow,
+    opacity: f64,
+) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        use objc::runtime::Object;
+        use objc::{msg_send, sel, sel_impl};
+        let ns_window = command_bar_window
+            .ns_window()
+            .map_err(|error| format!("Failed to access command bar NSWindow: {}", error))?
+            as *mut Object;
+        unsafe {
+            let _: () = msg_send![ns_window, setAlphaValue: opacity];
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use windows::Win32::Foundation::COLORREF;
+        use windows::Win32::UI::WindowsAndMessaging::{
+            GetWindowLongPtrW, SetLayeredWindowAttributes, SetWindowLongPtrW, GWL_EXSTYLE,
+            LWA_ALPHA, WS_EX_LAYERED,
+        };
+        let hwnd = command_bar_window
+            .hwnd()
+            .map_err(|error| format!("Failed to access command bar HWND: {}", error))?;
+        unsafe {
+            let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+            if ex_style & WS_EX_LAYERED.0 as isize == 0 {
+                SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style | WS_EX_LAYERED.0 as isize);
+            }
+            SetLayeredWindowAttributes(
+                hwnd,
+                COLORREF(0),
+                (opacity * 255.0).round() as u8,
+                LWA_ALPHA,
+            )
+            .map_err(|error| format!("Failed to set command bar opacity: {}", error))?;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let _ = command_bar_window;
+        let _ = opacity;
+    }
+
+    Ok(())
+}
+
+/// Whether the command bar fades in and out; on unless the user prefers it
+/// to appear instantly.
+#[cfg(target_os = "macos")]
+fn stored_command_bar_animations_enabled(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_ANIMATIONS_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true)
+}
+
+/// Whether action commands dismiss the command bar once they've run; off
+/// keeps it open and focused so several actions can be chained.
+#[cfg(desktop)]
+fn stored_close_after_action(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_CLOSE_AFTER_ACTION_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true)
+}
+
+/// Animate the command bar's `alphaValue` to `alpha` over
+/// `COMMAND_BAR_ANIMATION_DURATION` through the NSWindow's animator proxy.
+/// Returns immediately; the animation runs on its own.
+#[cfg(target_os = "macos")]
+fn animate_command_bar_alpha(command_bar_window: &tauri::WebviewWindow, alpha: f64) {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    let ns_window = match command_bar_window.ns_window() {
+        Ok(ns_window) => ns_window as *mut Object,
+        Err(error) => {
+            log::warn!("Failed to access command bar NSWindow: {}", error);
+            return;
+        }
+    };
+    unsafe {
+        let _: () = msg_send![class!(NSAnimationContext), beginGrouping];
+        let context: *mut Object = msg_send![class!(NSAnimationContext), currentContext];
+        let _: () = msg_send![context, setDuration: COMMAND_BAR_ANIMATION_DURATION.as_secs_f64()];
+        let animator: *mut Object = msg_send![ns_window, animator];
+        let _: () = msg_send![animator, setAlphaValue: alpha];
+        let _: () = msg_send![class!(NSAnimationContext), endGrouping];
+    }
+}
+
+/// Fade the command bar out, then hide it once the animation is done. The
+/// hide is skipped if `cancel_command_bar_fade_out` ran in the meantime.
+/// Opacity is reset afterwards so an instant show (animations turned off)
+/// isn't left at 0.
+#[cfg(target_os = "macos")]
+fn fade_out_command_bar(app: &tauri::AppHandle, command_bar_window: &tauri::WebviewWindow) {
+    let generation = {
+        let state = app.state::<CommandBarShortcutState>();
+        let mut fade_generation = lock_recovering(&state.fade_generation, FADE_STATE);
+        *fade_generation += 1;
+        *lock_recovering(&state.fading_out, FADE_STATE) = true;
+        *fade_generation
+    };
+    animate_command_bar_alpha(command_bar_window, 0.0);
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(COMMAND_BAR_ANIMATION_DURATION);
+        let main_thread_app = app.clone();
+        let result = app.run_on_main_thread(move || {
+            let app = &main_thread_app;
+            let state = app.state::<CommandBarShortcutState>();
+            {
+                let fade_generation = lock_recovering(&state.fade_generation, FADE_STATE);
+                if *fade_generation != generation {
+                    return;
+                }
+                *lock_recovering(&state.fading_out, FADE_STATE) = false;
+            }
+            let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
+                return;
+            };
+            if command_bar_window.hide().is_ok() {
+                command_bar_visibility_changed(app, false);
+            }
+            if let Err(error) =
+                apply_command_bar_opacity(&command_bar_window, stored_command_bar_opacity(app))
+            {
+                log::warn!("{}", error);
+            }
+        });
+        if let Err(error) = result {
+            log::warn!("Failed to schedule command bar hide: {}", error);
+        }
+    });
+}
+
+/// Stop an in-flight fade-out from hiding the bar; the caller restores its
+/// opacity. Returns whether one was in flight.
+#[cfg(target_os = "macos")]
+fn cancel_command_bar_fade_out(app: &tauri::AppHandle) -> bool {
+    let state = app.state::<CommandBarShortcutState>();
+    let mut fade_generation = lock_recovering(&state.fade_generation, FADE_STATE);
+    let mut fading_out = lock_recovering(&state.fading_out, FADE_STATE);
+    if !*fading_out {
+        return false;
+    }
+    *fade_generation += 1;
+    *fading_out = false;
+    true
+}
+
+/// Whether the command bar is visible but fading out on its way to hidden.
+#[cfg(desktop)]
+fn is_command_bar_fading_out(app: &tauri::AppHandle) -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        let state = app.state::<CommandBarShortcutState>();
+        let fading_out = *lock_recovering(&state.fading_out, FADE_STATE);
+        fading_out
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = app;
+        false
+    }
+}
+
+fn clamp_command_bar_size(width: f64, height: f64) -> Result<(f64, f64), String> {
+    if !width.is_finite() || !height.is_finite() {
+        return Err("Command bar size must be a finite number.".to_string());
+    }
+    Ok((
+        width.clamp(COMMAND_BAR_MIN_WIDTH, COMMAND_BAR_MAX_WIDTH),
+        height.clamp(COMMAND_BAR_MIN_HEIGHT, COMMAND_BAR_MAX_HEIGHT),
+    ))
+}
+
+/// Apply a pending one-shot size before the bar is shown, or restore the
+/// configured width and default height if the previous open used an override.
+#[cfg(desktop)]
+fn apply_command_bar_open_size(app: &tauri::AppHandle, command_bar_window: &tauri::WebviewWindow) {
+    let state = app.state::<CommandBarShortcutState>();
+    let next_size = lock_recovering(&state.next_open_size, OPEN_SIZE_STATE).take();
+    let mut overridden = lock_recovering(&state.open_size_overridden, OPEN_SIZE_STATE);
+
+    let (width, height) = match next_size {
+        Some(size) => {
+            *overridden = true;
+            size
+        }
+        None if *overridden => {
+            *overridden = false;
+            (
+                stored_command_bar_width(app),
+                stored_command_bar_height(app),
+            )
+        }
+        None => return,
+    };
+
+    if let Err(error) = command_bar_window.set_size(tauri::LogicalSize::new(width, height)) {
+        log::warn!("Failed to apply command bar open size: {}", error);
+    }
+}
+
+/// Resize the command bar while keeping its horizontal center fixed, so size
+/// changes grow/shrink symmetrically instead of drifting to the right.
+#[cfg(desktop)]
+fn resize_command_bar_keeping_center(
+    command_bar_window: &tauri::WebviewWindow,
+    width: f64,
+    height: f64,
+) -> tauri::Result<()> {
+    let scale_factor = command_bar_window.scale_factor()?;
+    let position = command_bar_window.outer_position()?;
+    let previous_size = command_bar_window.outer_size()?;
+    let center_x = position.x + previous_size.width as i32 / 2;
+
+    command_bar_window.set_size(tauri::LogicalSize::new(width, height))?;
+
+    let next_width = (width * scale_factor).round() as i32;
+    command_bar_window.set_position(tauri::PhysicalPosition::new(
+        center_x - next_width / 2,
+        position.y,
+    ))?;
+    Ok(())
+}
+
+#[cfg(desktop)]
+fn monitor_contains(monitor: &tauri::Monitor, x: f64, y: f64) -> bool {
+    let position = monitor.position();
+    let size = monitor.size();
+    x >= position.x as f64
+        && x < position.x as f64 + size.width as f64
+        && y >= position.y as f64
+        && y < position.y as f64 + size.height as f64
+}
+
+/// Physical bounds of one display, decoupled from `tauri::Monitor` so the
+/// placement math in `clamp_to_monitors` doesn't need a live display. Also
+/// returned to the frontend by `current_monitor_bounds`.
+#[cfg_attr(not(desktop), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MonitorBounds {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    scale_factor: f64,
+}
+
+impl From<&tauri::Monitor> for MonitorBounds {
+    fn from(monitor: &tauri::Monitor) -> Self {
+        let position = monitor.position();
+        let size = monitor.size();
+        Self {
+            x: position.x,
+            y: position.y,
+            width: size.width as i32,
+            height: size.height as i32,
+            scale_factor: monitor.scale_factor(),
+        }
+    }
+}
+
+/// Top-left corner for a window of `window_size` saved at `position`. When
+/// `position` is on one of `monitors` the window is kept there, clamped so
+/// it fits; otherwise (the display was disconnected) it snaps to the center
+/// of `monitors[0]`, which callers pass as the primary monitor. A window
+/// larger than the monitor is pinned to its top-left corner. `None` when no
+/// monitors are known.
+#[cfg(desktop)]
+fn clamp_to_monitors(
+    position: (i32, i32),
+    window_size: (i32, i32),
+    monitors: &[MonitorBounds],
+) -> Option<(i32, i32)> {
+    let (x, y) = position;
+    let (width, height) = window_size;
+    let containing = monitors.iter().find(|monitor| {
+        x >= monitor.x
+            && x < monitor.x + monitor.width
+            && y >= monitor.y
+            && y < monitor.y + monitor.height
+    });
+    match containing {
+        Some(monitor) => {
+            let max_x = monitor.x + monitor.width - width;
+            let max_y = monitor.y + monitor.height - height;
+            Some((x.min(max_x).max(monitor.x), y.min(max_y).max(monitor.y)))
+        }
+        None => {
+            let primary = monitors.first()?;
+            Some((
+                (primary.x + (primary.width - width) / 2).max(primary.x),
+                (primary.y + (primary.height - height) / 2).max(primary.y),
+            ))
+        }
+    }
+}
+
+/// Center the main window if its restored position no longer lands on any
+/// connected monitor, e.g. after unplugging the display it was last on.
+#[cfg(desktop)]
+fn ensure_main_window_on_screen(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) else {
+        return Ok(());
+    };
+    let position = main_window.outer_position()?;
+    let size = main_window.outer_size()?;
+    let center_x = position.x as f64 + size.width as f64 / 2.0;
+    let center_y = position.y as f64 + size.height as f64 / 2.0;
+    let on_screen = app
+        .available_monitors()?
+        .iter()
+        .any(|monitor| monitor_contains(monitor, center_x, center_y));
+    if !on_screen {
+        log::info!("Main window was restored off-screen; centering it.");
+        main_window.center()?;
+    }
+    Ok(())
+}
+
+/// Monitor containing the cursor, if the cursor position is known.
+#[cfg(desktop)]
+fn cursor_monitor(app: &tauri::AppHandle) -> Option<tauri::Monitor> {
+    let cursor = app.cursor_position().ok()?;
+    app.available_monitors()
+        .ok()?
+        .into_iter()
+        .find(|monitor| monitor_contains(monitor, cursor.x, cursor.y))
+}
+
+#[cfg(desktop)]
+fn parse_command_bar_position(value: &serde_json::Value) -> Option<CommandBarPosition> {
+    match value {
+        serde_json::Value::String(position) => match position.as_str() {
+            "center" => Some(CommandBarPosition::Center),
+            "top" => Some(CommandBarPosition::Top),
+            _ => None,
+        },
+        serde_json::Value::Object(position) => Some(CommandBarPosition::Saved {
+            x: i32::try_from(position.get("x")?.as_i64()?).ok()?,
+            y: i32::try_from(position.get("y")?.as_i64()?).ok()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Position setting for the command bar, centered unless the user chose
+/// otherwise or dragged it somewhere.
+#[cfg(desktop)]
+fn stored_command_bar_position(app: &tauri::AppHandle) -> CommandBarPosition {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_POSITION_STORE_KEY))
+        .as_ref()
+        .and_then(parse_command_bar_position)
+        .unwrap_or(CommandBarPosition::Center)
+}
+
+#[cfg(desktop)]
+fn parse_command_bar_anchor(anchor: &str) -> Option<CommandBarAnchor> {
+    match anchor {
+        "screen-center" => Some(CommandBarAnchor::ScreenCenter),
+        "monitor-center" => Some(CommandBarAnchor::MonitorCenter),
+        "cursor" => Some(CommandBarAnchor::Cursor),
+        _ => None,
+    }
+}
+
+/// Anchor setting for the command bar; the monitor under the cursor unless
+/// the user chose otherwise.
+#[cfg(desktop)]
+fn stored_command_bar_anchor(app: &tauri::AppHandle) -> CommandBarAnchor {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(COMMAND_BAR_ANCHOR_STORE_KEY))
+        .and_then(|value| value.as_str().and_then(parse_command_bar_anchor))
+        .unwrap_or(CommandBarAnchor::MonitorCenter)
+}
+
+/// Top-left corner for the `"cursor"` anchor: horizontally centered on the
+/// pointer and `COMMAND_BAR_CURSOR_OFFSET` below it, flipped above it when
+/// there's no room, and clamped to the cursor's monitor. `None` when the
+/// cursor position or its monitor is unknown.
+#[cfg(desktop)]
+fn cursor_anchored_position(
+    app: &tauri::AppHandle,
+    window_size: tauri::PhysicalSize<u32>,
+) -> Option<(i32, i32)> {
+    let cursor = app.cursor_position().ok()?;
+    let monitor = cursor_monitor(app)?;
+    let monitor_position = monitor.position();
+    let monitor_size = monitor.size();
+    let offset = (COMMAND_BAR_CURSOR_OFFSET * monitor.scale_factor()).round() as i32;
+    let (cursor_x, cursor_y) = (cursor.x.round() as i32, cursor.y.round() as i32);
+    let (width, height) = (window_size.width as i32, window_size.height as i32);
+    let max_x = monitor_position.x + monitor_size.width as i32 - width;
+    let max_y = monitor_position.y + monitor_size.height as i32 - height;
+
+    let x = cursor_x - width / 2;
+    let below = cursor_y + offset;
+    let y = if below <= max_y {
+        below
+    } else {
+        cursor_y - offset - height
+    };
+    Some((
+        x.min(max_x).max(monitor_position.x),
+        y.min(max_y).max(monitor_position.y),
+    ))
+}
+
+/// Place the command bar according to the anchor and position settings.
+/// The `"cursor"` anchor opens it next to the pointer. Otherwise center and
+/// top are relative to the monitor under the cursor, or the primary monitor
+/// for `"screen-center"` (falling back to the primary monitor, then to a
+/// plain `center()`). A saved position goes through `clamp_to_monitors`, so
+/// when its display is gone the bar snaps to the primary monitor instead of
+/// opening off-screen.
+#[cfg(desktop)]
+fn position_command_bar_on_active_monitor(
+    app: &tauri::AppHandle,
+    command_bar_window: &tauri::WebviewWindow,
+) -> tauri::Result<()> {
+    let anchor = stored_command_bar_anchor(app);
+    if anchor == CommandBarAnchor::Cursor {
+        let window_size = command_bar_window.outer_size()?;
+        if let Some((x, y)) = cursor_anchored_position(app, window_size) {
+            return command_bar_window.set_position(tauri::PhysicalPosition::new(x, y));
+        }
+    }
+
+    let position = stored_command_bar_position(app);
+    if let CommandBarPosition::Saved { x, y } = position {
+        let window_size = command_bar_window.outer_size()?;
+        let monitors: Vec<MonitorBounds> = app
+            .primary_monitor()?
+            .into_iter()
+            .chain(app.available_monitors()?)
+            .map(|monitor| MonitorBounds::from(&monitor))
+            .collect();
+        return match clamp_to_monitors(
+            (x, y),
+            (window_size.width as i32, window_size.height as i32),
+            &monitors,
+        ) {
+            Some((x, y)) => command_bar_window.set_position(tauri::PhysicalPosition::new(x, y)),
+            None => command_bar_window.center(),
+        };
+    }
+
+    let active_monitor = match anchor {
+        CommandBarAnchor::ScreenCenter => None,
+        CommandBarAnchor::MonitorCenter | CommandBarAnchor::Cursor => cursor_monitor(app),
+    };
+    let monitor = match active_monitor {
+        Some(monitor) => Some(monitor),
+        None => app.primary_monitor()?,
+    };
+    let Some(monitor) = monitor else {
+        return command_bar_window.center();
+    };
+
+    let window_size = command_bar_window.outer_size()?;
+    let monitor_position = monitor.position();
+    let monitor_size = monitor.size();
+    let centered_x =
+        monitor_position.x + (monitor_size.width as i32 - window_size.width as i32) / 2;
+    let y = if position == CommandBarPosition::Top {
+        monitor_position.y + (COMMAND_BAR_TOP_MARGIN * monitor.scale_factor()).round() as i32
+    } else {
+        monitor_position.y + (monitor_size.height as i32 - window_size.height as i32) / 3
+    };
+    command_bar_window.set_position(tauri::PhysicalPosition::new(centered_x, y))
+}
+
+#[cfg(desktop)]
+fn is_command_bar_visible(app: &tauri::AppHandle) -> bool {
+    app.get_webview_window(COMMAND_BAR_WINDOW_LABEL)
+        .is_some_and(|command_bar_window| command_bar_window.is_visible().unwrap_or(false))
+}
+
+#[cfg(desktop)]
+fn toggle_command_bar_window(app: &tauri::AppHandle) -> tauri::Result<()> {
+    // A bar that's fading out is already closing, so the press reopens it.
+    if is_command_bar_visible(app) && !is_command_bar_fading_out(app) {
+        hide_command_bar(app)
+    } else {
+        show_command_bar(app)
+    }
+}
+
+/// Hide the command bar without restoring focus elsewhere (see
+/// `dismiss_command_bar_window` for that). No-ops when it isn't shown.
+#[cfg(desktop)]
+fn hide_command_bar(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
+        return Ok(());
+    };
+    if command_bar_window.is_visible()? {
+        if is_command_bar_fading_out(app) {
+            return Ok(());
+        }
+        #[cfg(target_os = "macos")]
+        if stored_command_bar_animations_enabled(app) {
+            fade_out_command_bar(app, &command_bar_window);
+            return Ok(());
+        }
+        command_bar_window.hide()?;
+        command_bar_visibility_changed(app, false);
+    }
+    Ok(())
+}
+
+/// Open the command bar, creating it if needed and remembering the app that
+/// was frontmost so dismissing returns focus there. When it's already open
+/// this only refocuses it, cancelling a fade-out that's in progress.
+#[cfg(desktop)]
+fn show_command_bar(app: &tauri::AppHandle) -> tauri::Result<()> {
+    if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        if command_bar_window.is_visible()? {
+            #[cfg(target_os = "macos")]
+            if cancel_command_bar_fade_out(app) {
+                animate_command_bar_alpha(&command_bar_window, stored_command_bar_opacity(app));
+                let state = app.state::<CommandBarShortcutState>();
+                *lock_recovering(&state.last_shown_at, SHOWN_AT_STATE) = Some(Instant::now());
+            }
+            command_bar_window.set_focus()?;
+            emit_command_bar_focus_input(&command_bar_window);
+            return Ok(());
+        }
+    }
+
+    // In presentation mode the bar may still be closed, but never opened over
+    // a shared screen.
+    if stored_presentation_mode(app) {
+        log::info!("Presentation mode is on; not opening the command bar.");
+        return Ok(());
+    }
+
+    let command_bar_window = create_command_bar_window(app)?;
+    // A fade-out cut short by a direct hide mustn't hide this open later.
+    #[cfg(target_os = "macos")]
+    cancel_command_bar_fade_out(app);
+
+    // Snapshot the frontmost app before we steal focus so we can
+    // reactivate it when the command bar is dismissed.
+    #[cfg(target_os = "macos")]
+    {
+        let state = app.state::<CommandBarShortcutState>();
+        let pid = get_frontmost_app_pid();
+        *lock_recovering(&state.previous_frontmost_pid, PREVIOUS_APP_STATE) = pid;
+        *lock_recovering(&state.previous_frontmost_app_name, PREVIOUS_APP_STATE) =
+            get_frontmost_app_name();
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let hwnd = get_foreground_window();
+        let state = app.state::<CommandBarShortcutState>();
+        *lock_recovering(&state.previous_foreground_window, PREVIOUS_APP_STATE) = hwnd;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let window = if is_wayland_session() {
+            0
+        } else {
+            get_active_x11_window()
+        };
+        let state = app.state::<CommandBarShortcutState>();
+        *lock_recovering(&state.previous_active_window, PREVIOUS_APP_STATE) = window;
+    }
+
+    {
+        let state = app.state::<CommandBarShortcutState>();
+        *lock_recovering(&state.last_shown_at, SHOWN_AT_STATE) = Some(Instant::now());
+    }
+
+    apply_command_bar_open_size(app, &command_bar_window);
+    // Start fully transparent and fade up to the configured opacity.
+    #[cfg(target_os = "macos")]
+    let animate = stored_command_bar_animations_enabled(app);
+    #[cfg(target_os = "macos")]
+    if animate {
+        if let Err(error) = apply_command_bar_opacity(&command_bar_window, 0.0) {
+            log::warn!("{}", error);
+        }
+    }
+    command_bar_window.show()?;
+    if let Err(error) = position_command_bar_on_active_monitor(app, &command_bar_window) {
+        log::warn!("Failed to position command bar window: {}", error);
+    }
+    #[cfg(target_os = "macos")]
+    if animate {
+        animate_command_bar_alpha(&command_bar_window, stored_command_bar_opacity(app));
+    }
+    // The window is key for the whole fade, so focusing now (rather than
+    // when it finishes) means keystrokes typed during it aren't lost.
+    command_bar_window.set_focus()?;
+    emit_command_bar_focus_input(&command_bar_window);
+    command_bar_visibility_changed(app, true);
+    Ok(())
+}
+
+/// Ask the command bar page to focus its search input. `set_focus()` alone
+/// focuses the window but a reused webview doesn't always restore DOM focus.
+#[cfg(desktop)]
+fn emit_command_bar_focus_input(command_bar_window: &tauri::WebviewWindow) {
+    if let Err(error) =
+        command_bar_window.emit_to(COMMAND_BAR_WINDOW_LABEL, "command-bar://focus-input", ())
+    {
+        log::warn!("Failed to emit command bar focus event: {}", error);
+    }
+}
+
+/// Bring the command bar back to the front with its search input focused,
+/// for actions that keep it open instead of dismissing it.
+#[cfg(desktop)]
+fn refocus_command_bar_input(app: &tauri::AppHandle) {
+    let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
+        return;
+    };
+    if let Err(error) = command_bar_window.set_focus() {
+        log::warn!("Failed to refocus command bar window: {}", error);
+    }
+    emit_command_bar_focus_input(&command_bar_window);
+}
+
+/// Dismiss the command bar after an action ran, or refocus it when
+/// `commandBar.closeAfterAction` is off.
+#[cfg(desktop)]
+fn finish_command_bar_action(app: &tauri::AppHandle) {
+    if stored_close_after_action(app) {
+        dismiss_command_bar_window(app);
+    } else {
+        refocus_command_bar_input(app);
+    }
+}
+
+/// Whether a shortcut press should toggle the command bar, i.e. it isn't
+/// within `COMMAND_BAR_TOGGLE_DEBOUNCE` of the last accepted one.
+#[cfg(desktop)]
+fn accept_command_bar_toggle(app: &tauri::AppHandle) -> bool {
+    let state = app.state::<CommandBarShortcutState>();
+    let mut last_toggled_at = lock_recovering(&state.last_toggled_at, TOGGLED_AT_STATE);
+    let now = Instant::now();
+    if last_toggled_at.is_some_and(|toggled_at| now - toggled_at < COMMAND_BAR_TOGGLE_DEBOUNCE) {
+        return false;
+    }
+    *last_toggled_at = Some(now);
+    true
+}
+
+/// Whether command bar shortcut presses should go to the frontend as
+/// `shortcut://triggered` rather than toggle the bar.
+#[cfg(desktop)]
+fn shortcut_emits_event(app: &tauri::AppHandle) -> bool {
+    let state = app.state::<CommandBarShortcutState>();
+    let emits_event = *lock_recovering(&state.shortcut_emits_event, SHORTCUT_MODE_STATE);
+    emits_event
+}
+
+/// Emit `shortcut://triggered` to the main window for a command bar
+/// shortcut press.
+#[cfg(desktop)]
+fn emit_shortcut_triggered(app: &tauri::AppHandle, shortcut: &Shortcut) {
+    let preset_id = app
+        .state::<CommandBarShortcutState>()
+        .lock_active_presets()
+        .iter()
+        .find(|preset_id| shortcut_for_preset(preset_id).as_ref() == Some(shortcut))
+        .cloned();
+    let payload = ShortcutTriggered {
+        preset_id,
+        shortcut: shortcut_display(shortcut),
+    };
+    if let Err(error) = app.emit_to(MAIN_WINDOW_LABEL, "shortcut://triggered", payload) {
+        log::warn!("Failed to emit shortcut triggered event: {}", error);
+    }
+}
+
+/// Escape, registered only while the command bar window is focused and its
+/// page hasn't reported ready.
+#[cfg(desktop)]
+fn command_bar_escape_shortcut() -> Shortcut {
+    Shortcut::new(None, Code::Escape)
+}
+
+#[cfg(desktop)]
+fn set_command_bar_escape_enabled(app: &tauri::AppHandle, enabled: bool) {
+    let shortcut = command_bar_escape_shortcut();
+    let global_shortcut = app.global_shortcut();
+    if global_shortcut.is_registered(shortcut) == enabled {
+        return;
+    }
+    let result = if enabled {
+        global_shortcut.register(shortcut)
+    } else {
+        global_shortcut.unregister(shortcut)
+    };
+    match result {
+        Ok(()) if enabled => bind_shortcut_action(app, shortcut, ShortcutAction::DismissCommandBar),
+        Ok(()) => unbind_shortcut_action(app, &shortcut),
+        Err(error) => log::warn!("Failed to update command bar Escape shortcut: {}", error),
+    }
+}
+
+/// Global shortcut that shows/hides the main window (primary + Shift + M).
+#[cfg(desktop)]
+fn main_window_shortcut() -> Shortcut {
+    Shortcut::new(Some(primary_modifier() | Modifiers::SHIFT), Code::KeyM)
+}
+
+/// Hide the main window if it's the focused window, otherwise bring it
+/// forward. On macOS hiding goes through `hide_app()` so focus returns to
+/// the previous app, matching the command bar dismiss.
+#[cfg(desktop)]
+fn toggle_main_window(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) else {
+        return Ok(());
+    };
+
+    if main_window.is_visible()? && !main_window.is_minimized()? && main_window.is_focused()? {
+        #[cfg(target_os = "macos")]
+        hide_app();
+        #[cfg(not(target_os = "macos"))]
+        main_window.hide()?;
+        return Ok(());
+    }
+
+    bring_main_window_forward(app)
+}
+
+/// Show, unminimize, and focus the main window, first unhiding the app on
+/// macOS in case `dismiss_command_bar` hid it.
+#[cfg(desktop)]
+fn bring_main_window_forward(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) else {
+        return Ok(());
+    };
+    #[cfg(target_os = "macos")]
+    unhide_app();
+    main_window.show()?;
+    main_window.unminimize()?;
+    main_window.set_focus()
+}
+
+/// Whether the tray icon should be shown; on unless the user turned it off.
+#[cfg(desktop)]
+fn stored_tray_enabled(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(TRAY_ENABLED_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true)
+}
+
+/// Add the menu bar / system tray icon. Left-click toggles the main window;
+/// the menu opens the command bar, shows Kompose, or quits.
+#[cfg(desktop)]
+fn create_tray_icon(app: &tauri::AppHandle) -> tauri::Result<()> {
+    use tauri::menu::{Menu, MenuItem};
+    use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+
+    if app.tray_by_id(TRAY_ICON_ID).is_some() {
+        return Ok(());
+    }
+
+    let open_command_bar = MenuItem::with_id(
+        app,
+        TRAY_MENU_OPEN_COMMAND_BAR_ID,
+        "Open Command Bar",
+        true,
+        None::<&str>,
+    )?;
+    let show_main_window = MenuItem::with_id(
+        app,
+        TRAY_MENU_SHOW_MAIN_WINDOW_ID,
+        "Show Kompose",
+        true,
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, TRAY_MENU_QUIT_ID, "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&open_command_bar, &show_main_window, &quit])?;
+
+    let mut tray_builder = TrayIconBuilder::with_id(TRAY_ICON_ID)
+        .tooltip("Kompose")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| {
+            let result = match event.id().as_ref() {
+                TRAY_MENU_OPEN_COMMAND_BAR_ID => show_command_bar(app),
+                TRAY_MENU_SHOW_MAIN_WINDOW_ID => bring_main_window_forward(app),
+                TRAY_MENU_QUIT_ID => {
+                    unregister_all_shortcuts(app);
+                    app.exit(0);
+                    Ok(())
+                }
+                _ => Ok(()),
+            };
+            if let Err(error) = result {
+                log::warn!("Failed to handle tray menu action: {}", error);
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                if let Err(error) = toggle_main_window(tray.app_handle()) {
+                    log::warn!("Failed to toggle main window from tray: {}", error);
+                }
+            }
+        });
+
+    if let Some(icon) = app.default_window_icon() {
+        tray_builder = tray_builder.icon(icon.clone());
+    }
+
+    tray_builder.build(app)?;
+    Ok(())
+}
+
+#[cfg(desktop)]
+fn unregister_all_shortcuts(app: &tauri::AppHandle) {
+    if let Err(error) = app.global_shortcut().unregister_all() {
+        log::warn!("Failed to unregister global shortcuts: {}", error);
+    }
+    let state = app.state::<CommandBarShortcutState>();
+    lock_recovering(&state.shortcut_actions, SHORTCUT_ACTIONS_STATE).clear();
+}
+
+/// Replace the registered command bar shortcuts with `preset_ids`, updating
+/// the in-memory active presets on success and returning them deduplicated.
+/// The swap is all-or-nothing: if any new binding fails to register, the
+/// ones registered so far are rolled back and the old bindings stay active.
+/// While the shortcut is disabled or onboarding hasn't finished only the
+/// active presets change; they get registered once that's no longer the case.
+#[cfg(desktop)]
+fn switch_shortcut_presets(
+    app: &tauri::AppHandle,
+    state: &CommandBarShortcutState,
+    preset_ids: &[String],
+) -> Result<Vec<String>, KomposeError> {
+    let global_shortcut = app.global_shortcut();
+    switch_shortcut_presets_with(
+        state,
+        preset_ids,
+        !stored_onboarding_completed(app),
+        |preset_id| {
+            shortcut_for_preset(preset_id)
+                .is_some_and(|shortcut| global_shortcut.is_registered(shortcut))
+        },
+        |preset_id| register_shortcut_preset(app, preset_id),
+        |preset_id| unregister_shortcut_preset(app, preset_id),
+    )
+}
+
+/// `switch_shortcut_presets` against caller-supplied registry steps, so the
+/// register/rollback decisions don't need a real global shortcut manager.
+#[cfg(desktop)]
+fn switch_shortcut_presets_with(
+    state: &CommandBarShortcutState,
+    preset_ids: &[String],
+    registration_deferred: bool,
+    is_registered: impl Fn(&str) -> bool,
+    mut register: impl FnMut(&str) -> Result<(), KomposeError>,
+    mut unregister: impl FnMut(&str),
+) -> Result<Vec<String>, KomposeError> {
+    let mut next_presets: Vec<String> = Vec::new();
+    for preset_id in preset_ids {
+        let preset_id = validate_shortcut_preset(preset_id)?;
+        if !next_presets.contains(&preset_id) {
+            next_presets.push(preset_id);
+        }
+    }
+    if next_presets.is_empty() {
+        return Err(KomposeError::Other(
+            "At least one command bar shortcut is required.".to_string(),
+        ));
+    }
+
+    let previous_presets = state.lock_active_presets().clone();
+
+    let enabled = *state.lock_enabled();
+    if !enabled || registration_deferred {
+        *state.lock_active_presets() = next_presets.clone();
+        return Ok(next_presets);
+    }
+
+    let previously_registered = state.lock_registration().registered;
+    // Re-selecting the same presets retries a registration that failed earlier.
+    if previous_presets == next_presets && previously_registered {
+        return Ok(next_presets);
+    }
+
+    // Register the new bindings before dropping the old ones, so a conflict
+    // leaves the previous shortcuts working instead of none at all.
+    let mut newly_registered: Vec<&String> = Vec::new();
+    for preset_id in &next_presets {
+        if is_registered(preset_id) {
+            continue;
+        }
+        let result = register(preset_id);
+        if result.is_err() {
+            for preset_id in &newly_registered {
+                unregister(preset_id);
+            }
+            if !previously_registered {
+                record_shortcut_registration(state, &result);
+            }
+            result?;
+        }
+        newly_registered.push(preset_id);
+    }
+    for preset_id in &previous_presets {
+        if !next_presets.contains(preset_id) {
+            unregister(preset_id);
+        }
+    }
+    record_shortcut_registration(state, &Ok(()));
+
+    *state.lock_active_presets() = next_presets.clone();
+    Ok(next_presets)
+}
+
+#[cfg(desktop)]
+fn persist_shortcut_presets(app: &tauri::AppHandle, preset_ids: &[String]) -> Result<(), String> {
+    let store = settings_store(app)?;
+    store.set(SHORTCUT_PRESET_IDS_STORE_KEY, preset_ids.to_vec());
+    if let Some(primary) = preset_ids.first() {
+        store.set(SHORTCUT_PRESET_STORE_KEY, primary.as_str());
+    }
+    store
+        .save()
+        .map_err(|error| format!("Failed to persist shortcut preset: {}", error))
+}
+
+/// Bind the command bar to `preset_id` alone, replacing any other bindings.
+#[tauri::command]
+fn set_command_bar_shortcut_preset(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+    preset_id: String,
+) -> Result<(), KomposeError> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        let _ = state;
+        let _ = preset_id;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let preset_ids = switch_shortcut_presets(&app, &state, &[preset_id])?;
+        persist_shortcut_presets(&app, &preset_ids)?;
+    }
+
+    Ok(())
+}
+
+/// Bind the command bar to an arbitrary modifier + key combination, e.g.
+/// `(["ctrl", "alt"], "J")`, replacing any other bindings.
+#[tauri::command]
+fn set_command_bar_shortcut_custom(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+    modifiers: Vec<String>,
+    key: String,
+) -> Result<(), KomposeError> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        let _ = state;
+        let _ = modifiers;
+        let _ = key;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let shortcut =
+            custom_shortcut(&modifiers, &key).map_err(KomposeError::ShortcutUnsupported)?;
+        let preset_id = custom_shortcut_preset_id(&shortcut);
+        let preset_ids = switch_shortcut_presets(&app, &state, &[preset_id])?;
+        persist_shortcut_presets(&app, &preset_ids)?;
+    }
+
+    Ok(())
+}
+
+/// Bind the command bar to a combo as the settings UI displays it, e.g.
+/// `"Cmd+Shift+K"`, replacing any other bindings.
+#[tauri::command]
+fn set_command_bar_shortcut_from_string(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+    combo: String,
+) -> Result<(), KomposeError> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        let _ = state;
+        let _ = combo;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let shortcut = shortcut_from_string(&combo).map_err(KomposeError::ShortcutUnsupported)?;
+        let preset_id = custom_shortcut_preset_id(&shortcut);
+        let preset_ids = switch_shortcut_presets(&app, &state, &[preset_id])?;
+        persist_shortcut_presets(&app, &preset_ids)?;
+    }
+
+    Ok(())
+}
+
+/// Bind `preset_id` to the command bar in addition to the existing bindings,
+/// e.g. to use a different shortcut on a second keyboard.
+#[tauri::command]
+fn add_command_bar_shortcut(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+    preset_id: String,
+) -> Result<(), KomposeError> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        let _ = state;
+        let _ = preset_id;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let preset_id = validate_shortcut_preset(&preset_id)?;
+        let enabled = *state.lock_enabled();
+        let mut guard = state.lock_active_presets();
+        if guard.contains(&preset_id) {
+            return Ok(());
+        }
+        if enabled && stored_onboarding_completed(&app) {
+            let result = register_shortcut_preset(&app, &preset_id);
+            record_shortcut_registration(&state, &result);
+            result?;
+        }
+        guard.push(preset_id);
+        let preset_ids = guard.clone();
+        drop(guard);
+        persist_shortcut_presets(&app, &preset_ids)?;
+    }
+
+    Ok(())
+}
+
+/// Unbind `preset_id` from the command bar, leaving its other bindings
+/// registered. The last binding can't be removed; disable the shortcut
+/// instead.
+#[tauri::command]
+fn remove_command_bar_shortcut(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+    preset_id: String,
+) -> Result<(), KomposeError> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        let _ = state;
+        let _ = preset_id;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let preset_id = preset_id.trim();
+        let enabled = *state.lock_enabled();
+        let mut guard = state.lock_active_presets();
+        let Some(index) = guard.iter().position(|id| id == preset_id) else {
+            return Ok(());
+        };
+        if guard.len() == 1 {
+            return Err(KomposeError::Other(
+                "At least one command bar shortcut is required.".to_string(),
+            ));
+        }
+        if enabled {
+            unregister_shortcut_preset(&app, preset_id);
+        }
+        guard.remove(index);
+        let preset_ids = guard.clone();
+        drop(guard);
+        persist_shortcut_presets(&app, &preset_ids)?;
+    }
+
+    Ok(())
+}
+
+/// Temporarily suspend or restore the command bar shortcut (e.g. while a game
+/// needs the same keys) without forgetting the selected presets.
+#[tauri::command]
+fn set_command_bar_shortcut_enabled(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+    enabled: bool,
+) -> Result<(), KomposeError> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        let _ = state;
+        let _ = enabled;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let mut enabled_guard = state.lock_enabled();
+        if *enabled_guard != enabled {
+            let preset_ids = state.lock_active_presets().clone();
+            if enabled {
+                // Until onboarding completes only the flag changes, as at launch.
+                if stored_onboarding_completed(&app) {
+                    let result = register_shortcut_presets(&app, &preset_ids);
+                    record_shortcut_registration(&state, &result);
+                    result?;
+                }
+            } else {
+                for preset_id in &preset_ids {
+                    unregister_shortcut_preset(&app, preset_id);
+                }
+                *state.lock_registration() = ShortcutRegistration::default();
+            }
+            *enabled_guard = enabled;
+        }
+        drop(enabled_guard);
+
+        let store = settings_store(&app)?;
+        store.set(SHORTCUT_ENABLED_STORE_KEY, enabled);
+        store
+            .save()
+            .map_err(|error| format!("Failed to persist shortcut enabled setting: {}", error))?;
+    }
+
+    Ok(())
+}
+
+/// Choose what the command bar shortcut does: toggle the bar (the default)
+/// or, with `emit_event`, only emit `shortcut://triggered` to the main window.
+#[tauri::command]
+fn set_command_bar_shortcut_emits_event(
+    state: tauri::State<CommandBarShortcutState>,
+    emit_event: bool,
+) -> Result<(), KomposeError> {
+    *lock_recovering(&state.shortcut_emits_event, SHORTCUT_MODE_STATE) = emit_event;
+    Ok(())
+}
+
+fn validate_shortcut_profile_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Shortcut profile name cannot be empty.".to_string());
+    }
+    if name.chars().count() > SHORTCUT_PROFILE_NAME_MAX_LEN {
+        return Err(format!(
+            "Shortcut profile name must be at most {} characters.",
+            SHORTCUT_PROFILE_NAME_MAX_LEN
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Shortcut profile name '{}' may only contain letters, numbers, spaces, '-' and '_'.",
+            name
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn stored_shortcut_profiles(
+    store: &Store<tauri::Wry>,
+) -> serde_json::Map<String, serde_json::Value> {
+    match store.get(SHORTCUT_PROFILES_STORE_KEY) {
+        Some(serde_json::Value::Object(profiles)) => profiles,
+        _ => serde_json::Map::new(),
+    }
+}
+
+/// Snapshot of the settings the built-in default profile restores.
+fn default_shortcut_profile_settings() -> serde_json::Map<String, serde_json::Value> {
+    let mut settings = serde_json::Map::new();
+    settings.insert(
+        SHORTCUT_PRESET_STORE_KEY.to_string(),
+        serde_json::Value::from(DEFAULT_SHORTCUT_PRESET_ID),
+    );
+    settings
+}
+
+/// Save the current shortcut-related settings under `name` so they can be
+/// restored later with `apply_shortcut_profile`.
+#[tauri::command]
+fn save_shortcut_profile(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    let name = validate_shortcut_profile_name(&name)?;
+    if name.eq_ignore_ascii_case(DEFAULT_SHORTCUT_PROFILE_NAME) {
+        return Err(format!(
+            "The built-in '{}' profile cannot be overwritten.",
+            DEFAULT_SHORTCUT_PROFILE_NAME
+        ));
+    }
+
+    let store = settings_store(&app)?;
+    let mut snapshot = serde_json::Map::new();
+    for key in SHORTCUT_PROFILE_SETTING_KEYS {
+        if let Some(value) = store.get(*key) {
+            snapshot.insert((*key).to_string(), value);
+        }
+    }
+
+    let mut profiles = stored_shortcut_profiles(&store);
+    profiles.insert(name, serde_json::Value::Object(snapshot));
+    store.set(
+        SHORTCUT_PROFILES_STORE_KEY,
+        serde_json::Value::Object(profiles),
+    );
+    store
+        .save()
+        .map_err(|error| format!("Failed to save shortcut profile: {}", error))
+}
+
+#[tauri::command]
+fn list_shortcut_profiles(app: tauri::AppHandle) -> Result<Vec<ShortcutProfile>, String> {
+    let store = settings_store(&app)?;
+    let mut names: Vec<String> = stored_shortcut_profiles(&store).keys().cloned().collect();
+    names.sort();
+
+    let mut profiles = vec![ShortcutProfile {
+        name: DEFAULT_SHORTCUT_PROFILE_NAME.to_string(),
+        built_in: true,
+    }];
+    profiles.extend(names.into_iter().map(|name| ShortcutProfile {
+        name,
+        built_in: false,
+    }));
+    Ok(profiles)
+}
+
+/// Restore a saved profile's settings into the store, re-register the
+/// shortcut it selects, and move an existing command bar to the profile's
+/// layout. Emits `settings://profile-applied` with the name.
+#[tauri::command]
+fn apply_shortcut_profile(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+    name: String,
+) -> Result<(), KomposeError> {
+    let name = validate_shortcut_profile_name(&name)?;
+    let store = settings_store(&app)?;
+
+    let settings = if name.eq_ignore_ascii_case(DEFAULT_SHORTCUT_PROFILE_NAME) {
+        default_shortcut_profile_settings()
+    } else {
+        match stored_shortcut_profiles(&store).remove(&name) {
+            Some(serde_json::Value::Object(settings)) => settings,
+            _ => {
+                return Err(KomposeError::Other(format!(
+                    "Shortcut profile '{}' does not exist.",
+                    name
+                )))
+            }
+        }
+    };
+
+    let preset_ids: Vec<String> = match settings.get(SHORTCUT_PRESET_IDS_STORE_KEY) {
+        Some(serde_json::Value::Array(values)) => values
+            .iter()
+            .filter_map(|value| value.as_str())
+            .map(str::to_string)
+            .collect(),
+        _ => vec![settings
+            .get(SHORTCUT_PRESET_STORE_KEY)
+            .and_then(|value| value.as_str())
+            .unwrap_or(DEFAULT_SHORTCUT_PRESET_ID)
+            .to_string()],
+    };
+
+    // Re-register first so a conflicting shortcut leaves stored settings untouched.
+    #[cfg(desktop)]
+    switch_shortcut_presets(&app, &state, &preset_ids)?;
+    #[cfg(not(desktop))]
+    let _ = (&state, &preset_ids);
+
+    for key in SHORTCUT_PROFILE_SETTING_KEYS {
+        match settings.get(*key) {
+            Some(value) => store.set(*key, value.clone()),
+            None => {
+                store.delete(*key);
+            }
+        }
+    }
+    store
+        .save()
+        .map_err(|error| format!("Failed to save applied shortcut profile: {}", error))?;
+
+    #[cfg(desktop)]
+    if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        command_bar_window
+            .set_size(tauri::LogicalSize::new(
+                stored_command_bar_width(&app),
+                stored_command_bar_height(&app),
+            ))
+            .map_err(|error| format!("Failed to resize command bar window: {}", error))?;
+        position_command_bar_on_active_monitor(&app, &command_bar_window)
+            .map_err(|error| format!("Failed to position command bar window: {}", error))?;
+    }
+
+    if let Err(error) = app.emit("settings://profile-applied", &name) {
+        log::warn!("Failed to emit shortcut profile event: {}", error);
+    }
+
+    Ok(())
+}
+
+fn unix_millis_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Usage history from the store, skipping malformed entries and ones older
+/// than `RECENT_COMMAND_MAX_AGE`.
+fn stored_recent_commands(store: &Store<tauri::Wry>, now: u64) -> Vec<RecentCommand> {
+    let Some(serde_json::Value::Object(entries)) = store.get(RECENT_COMMANDS_STORE_KEY) else {
+        return Vec::new();
+    };
+    let max_age = RECENT_COMMAND_MAX_AGE.as_millis() as u64;
+    entries
+        .into_iter()
+        .filter_map(|(id, entry)| {
+            Some(RecentCommand {
+                use_count: entry.get("useCount")?.as_u64()?,
+                last_used_at: entry.get("lastUsedAt")?.as_u64()?,
+                id,
+            })
+        })
+        .filter(|command| now.saturating_sub(command.last_used_at) <= max_age)
+        .collect()
+}
+
+/// History cap from `history.maxEntries`, falling back to the default when
+/// it's missing or not a positive integer.
+fn stored_recent_commands_max(store: &Store<tauri::Wry>) -> usize {
+    store
+        .get(HISTORY_MAX_ENTRIES_STORE_KEY)
+        .and_then(|value| value.as_u64())
+        .filter(|max| *max > 0)
+        .and_then(|max| usize::try_from(max).ok())
+        .unwrap_or(RECENT_COMMANDS_DEFAULT_MAX)
+}
+
+/// Frecency: the use count halves for every week since the last use, so a
+/// command used often last month ranks below one used twice today.
+fn recent_command_score(command: &RecentCommand, now: u64) -> f64 {
+    const WEEK_MILLIS: f64 = 7.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+    let age_weeks = now.saturating_sub(command.last_used_at) as f64 / WEEK_MILLIS;
+    command.use_count as f64 * 0.5_f64.powf(age_weeks)
+}
+
+/// Bump the usage count and timestamp for a command bar action so it shows
+/// up in `get_recent_commands`. Prunes the history to
+/// `stored_recent_commands_max` entries.
+#[tauri::command]
+fn record_command_usage(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    let id = id.trim();
+    if id.is_empty() {
+        return Err("Command id cannot be empty.".to_string());
+    }
+    if id.len() > RECENT_COMMAND_ID_MAX_LEN {
+        return Err(format!(
+            "Command id must be at most {} bytes.",
+            RECENT_COMMAND_ID_MAX_LEN
+        ));
+    }
+
+    let store = settings_store(&app)?;
+    let now = unix_millis_now();
+    let mut commands = stored_recent_commands(&store, now);
+    match commands.iter_mut().find(|command| command.id == id) {
+        Some(command) => {
+            command.use_count = command.use_count.saturating_add(1);
+            command.last_used_at = now;
+        }
+        None => commands.push(RecentCommand {
+            id: id.to_string(),
+            use_count: 1,
+            last_used_at: now,
+        }),
+    }
+    commands.sort_by_key(|command| std::cmp::Reverse(command.last_used_at));
+    commands.truncate(stored_recent_commands_max(&store));
+    write_recent_commands(&store, commands);
+    schedule_settings_save(&app);
+    Ok(())
+}
+
+/// Replace the stored usage history with `commands`.
+fn write_recent_commands(store: &Store<tauri::Wry>, commands: Vec<RecentCommand>) {
+    let entries: serde_json::Map<String, serde_json::Value> = commands
+        .into_iter()
+        .map(|command| {
+            (
+                command.id,
+                serde_json::json!({
+                    "useCount": command.use_count,
+                    "lastUsedAt": command.last_used_at,
+                }),
+            )
+        })
+        .collect();
+    store.set(
+        RECENT_COMMANDS_STORE_KEY,
+        serde_json::Value::Object(entries),
+    );
+}
+
+/// Set how many command ids the usage history keeps, pruning the least
+/// recently used right away if it now holds more.
+#[tauri::command]
+fn set_history_max_entries(app: tauri::AppHandle, max: usize) -> Result<(), String> {
+    if !HISTORY_MAX_ENTRIES_RANGE.contains(&max) {
+        return Err(format!(
+            "History size must be between {} and {}.",
+            HISTORY_MAX_ENTRIES_RANGE.start(),
+            HISTORY_MAX_ENTRIES_RANGE.end()
+        ));
+    }
+
+    let store = settings_store(&app)?;
+    store.set(HISTORY_MAX_ENTRIES_STORE_KEY, max);
+    let mut commands = stored_recent_commands(&store, unix_millis_now());
+    commands.sort_by_key(|command| std::cmp::Reverse(command.last_used_at));
+    commands.truncate(max);
+    write_recent_commands(&store, commands);
+    schedule_settings_save(&app);
+    Ok(())
+}
+
+/// Up to `limit` recently used command bar actions, best first by a mix of
+/// how recently and how often they were used.
+#[tauri::command]
+fn get_recent_commands(app: tauri::AppHandle, limit: usize) -> Result<Vec<RecentCommand>, String> {
+    let store = settings_store(&app)?;
+    let now = unix_millis_now();
+    let mut commands = stored_recent_commands(&store, now);
+    commands.sort_by(|a, b| {
+        recent_command_score(b, now)
+            .total_cmp(&recent_command_score(a, now))
+            .then_with(|| b.last_used_at.cmp(&a.last_used_at))
+    });
+    commands.truncate(limit);
+    Ok(commands)
+}
+
+#[tauri::command]
+async fn get_network_status(app: tauri::AppHandle) -> NetworkStatus {
+    let online = tauri::async_runtime::spawn_blocking(move || current_network_status(&app))
+        .await
+        .unwrap_or(false);
+    NetworkStatus { online }
+}
+
+/// Primary preset that is currently active, so the settings UI can reflect
+/// the real registration instead of assuming the default.
+#[tauri::command]
+fn get_command_bar_shortcut_preset(
+    state: tauri::State<CommandBarShortcutState>,
+) -> Result<String, KomposeError> {
+    let guard = state.lock_active_presets();
+    Ok(guard
+        .first()
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_SHORTCUT_PRESET_ID.to_string()))
+}
+
+/// Every preset bound to the command bar, primary first.
+#[tauri::command]
+fn get_command_bar_shortcut_presets(
+    state: tauri::State<CommandBarShortcutState>,
+) -> Result<Vec<String>, KomposeError> {
+    let guard = state.lock_active_presets();
+    Ok(guard.clone())
+}
+
+/// Active presets plus whether their registration succeeded, so the settings
+/// UI can surface "in use by another app" instead of silently failing.
+#[tauri::command]
+fn command_bar_shortcut_status(
+    state: tauri::State<CommandBarShortcutState>,
+) -> Result<ShortcutStatus, KomposeError> {
+    let active_presets = state.lock_active_presets().clone();
+    let active_preset = active_presets
+        .first()
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_SHORTCUT_PRESET_ID.to_string());
+    let enabled = *state.lock_enabled();
+    let registration = state.lock_registration().clone();
+    Ok(ShortcutStatus {
+        active_preset,
+        active_presets,
+        enabled,
+        registered: registration.registered,
+        error: registration.error,
+    })
+}
+
+/// Resize the command bar height to fit its results, keeping the current
+/// width, and remember it as the size to create the window at next time.
+/// No-ops when the command bar window doesn't exist.
+#[tauri::command]
+fn set_command_bar_height(app: tauri::AppHandle, height: f64) -> Result<(), String> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        let _ = height;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        if !height.is_finite() {
+            return Err("Command bar height must be a finite number.".to_string());
+        }
+        let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
+            return Ok(());
+        };
+
+        let height = height.clamp(COMMAND_BAR_MIN_HEIGHT, COMMAND_BAR_MAX_HEIGHT);
+        let store = settings_store(&app)?;
+        store.set(COMMAND_BAR_HEIGHT_STORE_KEY, height);
+        schedule_settings_save(&app);
+
+        let scale_factor = command_bar_window
+            .scale_factor()
+            .map_err(|error| format!("Failed to read command bar scale factor: {}", error))?;
+        let width = command_bar_window
+            .inner_size()
+            .map_err(|error| format!("Failed to read command bar size: {}", error))?
+            .to_logical::<f64>(scale_factor)
+            .width;
+
+        resize_command_bar_keeping_center(&command_bar_window, width, height)
+            .map_err(|error| format!("Failed to resize command bar window: {}", error))?;
+    }
+
+    Ok(())
+}
+
+/// Set the command bar width (e.g. measured from its content), keeping it
+/// horizontally centered, and remember it for later opens.
+#[tauri::command]
+fn set_command_bar_width(app: tauri::AppHandle, width: f64) -> Result<(), String> {
+    if !width.is_finite() {
+        return Err("Command bar width must be a finite number.".to_string());
+    }
+    let width = width.clamp(COMMAND_BAR_MIN_WIDTH, COMMAND_BAR_MAX_WIDTH);
+
+    let store = settings_store(&app)?;
+    store.set(COMMAND_BAR_WIDTH_STORE_KEY, width);
+    schedule_settings_save(&app);
+
+    #[cfg(desktop)]
+    if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        let scale_factor = command_bar_window
+            .scale_factor()
+            .map_err(|error| format!("Failed to read command bar scale factor: {}", error))?;
+        let height = command_bar_window
+            .inner_size()
+            .map_err(|error| format!("Failed to read command bar size: {}", error))?
+            .to_logical::<f64>(scale_factor)
+            .height;
+
+        resize_command_bar_keeping_center(&command_bar_window, width, height)
+            .map_err(|error| format!("Failed to resize command bar window: {}", error))?;
+    }
+
+    Ok(())
+}
+
+/// Remember where the user dragged the command bar so later opens restore
+/// it there. No-ops when the command bar window doesn't exist.
+#[tauri::command]
+fn save_command_bar_position(app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
+            return Ok(());
+        };
+        let position = command_bar_window
+            .outer_position()
+            .map_err(|error| format!("Failed to read command bar position: {}", error))?;
+
+        let store = settings_store(&app)?;
+        store.set(
+            COMMAND_BAR_POSITION_STORE_KEY,
+            serde_json::json!({ "x": position.x, "y": position.y }),
+        );
+        schedule_settings_save(&app);
+    }
+
+    Ok(())
+}
+
+/// Choose where the command bar opens: `"screen-center"` (primary monitor),
+/// `"monitor-center"` (monitor under the cursor, the default), or
+/// `"cursor"` (next to the pointer).
+#[tauri::command]
+fn set_command_bar_anchor(app: tauri::AppHandle, anchor: String) -> Result<(), String> {
+    let anchor = anchor.trim();
+    if !["screen-center", "monitor-center", "cursor"].contains(&anchor) {
+        return Err(format!(
+            "Unsupported command bar anchor '{}'. Expected screen-center, monitor-center, or cursor.",
+            anchor
+        ));
+    }
+
+    let store = settings_store(&app)?;
+    store.set(COMMAND_BAR_ANCHOR_STORE_KEY, anchor);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save command bar anchor: {}", error))
+}
+
+/// Turn presentation mode on or off. While on, the shortcut and tray won't
+/// open the command bar, so it can't pop up over a shared screen.
+#[tauri::command]
+fn set_presentation_mode(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let store = settings_store(&app)?;
+    store.set(PRESENTATION_MODE_STORE_KEY, enabled);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save presentation mode: {}", error))
+}
+
+/// Let the command bar behave like a normal window instead of floating above
+/// everything, and remember the choice. Focus-lost auto-hide is unaffected.
+#[tauri::command]
+fn set_command_bar_always_on_top(app: tauri::AppHandle, on_top: bool) -> Result<(), String> {
+    let store = settings_store(&app)?;
+    store.set(COMMAND_BAR_ALWAYS_ON_TOP_STORE_KEY, on_top);
+    store.save().map_err(|error| {
+        format!(
+            "Failed to save command bar always-on-top setting: {}",
+            error
+        )
+    })?;
+
+    #[cfg(desktop)]
+    if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        command_bar_window
+            .set_always_on_top(on_top)
+            .map_err(|error| format!("Failed to update command bar window level: {}", error))?;
+        // `set_always_on_top` uses the floating level; restore the
+        // above-fullscreen level and Space behavior the bar is created with.
+        #[cfg(target_os = "macos")]
+        if on_top {
+            configure_command_bar_for_fullscreen_spaces(&command_bar_window);
+        }
+    }
+
+    Ok(())
+}
+
+/// Hide the command bar from screen capture (screenshots, recordings, screen
+/// sharing) while it keeps working normally, and remember the choice. Unlike
+/// presentation mode the bar still opens. Only macOS (`sharingType` none)
+/// and Windows (`WDA_EXCLUDEFROMCAPTURE`) support it.
+#[tauri::command]
+fn set_command_bar_capture_excluded(app: tauri::AppHandle, excluded: bool) -> Result<(), String> {
+    let store = settings_store(&app)?;
+    store.set(COMMAND_BAR_CAPTURE_EXCLUDED_STORE_KEY, excluded);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save command bar capture setting: {}", error))?;
+
+    #[cfg(desktop)]
+    if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        command_bar_window
+            .set_content_protected(excluded)
+            .map_err(|error| format!("Failed to update command bar capture setting: {}", error))?;
+    }
+
+    Ok(())
+}
+
+/// Make the whole command bar window translucent, clamped to 0.3–1.0, and
+/// remember it for later opens. Only macOS and Windows apply it.
+#[tauri::command]
+fn set_command_bar_opacity(app: tauri::AppHandle, opacity: f64) -> Result<(), String> {
+    if !opacity.is_finite() {
+        return Err("Command bar opacity must be a finite number.".to_string());
+    }
+    let opacity = opacity.clamp(COMMAND_BAR_MIN_OPACITY, COMMAND_BAR_MAX_OPACITY);
+
+    let store = settings_store(&app)?;
+    store.set(COMMAND_BAR_OPACITY_STORE_KEY, opacity);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save command bar opacity: {}", error))?;
+
+    #[cfg(desktop)]
+    if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        apply_command_bar_opacity(&command_bar_window, opacity)?;
+    }
+
+    Ok(())
+}
+
+/// Turn the command bar's open/close fade on or off. Only macOS animates.
+#[tauri::command]
+fn set_command_bar_animations_enabled(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let store = settings_store(&app)?;
+    store.set(COMMAND_BAR_ANIMATIONS_STORE_KEY, enabled);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save command bar animation setting: {}", error))
+}
+
+/// Flip `commandBar.closeAfterAction` and return the new value. Emits
+/// `settings://close-after-action-changed` with it once saved.
+#[tauri::command]
+fn toggle_command_bar_close_after_action(app: tauri::AppHandle) -> Result<bool, String> {
+    let store = settings_store(&app)?;
+    let close_after_action = !store
+        .get(COMMAND_BAR_CLOSE_AFTER_ACTION_STORE_KEY)
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true);
+    store.set(COMMAND_BAR_CLOSE_AFTER_ACTION_STORE_KEY, close_after_action);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save close-after-action setting: {}", error))?;
+
+    if let Err(error) = app.emit("settings://close-after-action-changed", close_after_action) {
+        log::warn!("Failed to emit close-after-action event: {}", error);
+    }
+    Ok(close_after_action)
+}
+
+/// Turn background update checks on or off. Checking on demand is unaffected.
+#[tauri::command]
+fn set_auto_update_enabled(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let store = settings_store(&app)?;
+    store.set(AUTO_UPDATE_ENABLED_STORE_KEY, enabled);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save auto-update setting: {}", error))
+}
+
+/// Physical position, size, and scale factor of the monitor under the cursor
+/// (else the primary one), so the command bar can cap its height to the
+/// visible area. Errors only when no monitor can be found at all.
+#[tauri::command]
+fn current_monitor_bounds(app: tauri::AppHandle) -> Result<MonitorBounds, String> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        return Err("Monitor bounds are only available on desktop.".to_string());
+    }
+
+    #[cfg(desktop)]
+    {
+        let monitor = match cursor_monitor(&app) {
+            Some(monitor) => Some(monitor),
+            None => app
+                .primary_monitor()
+                .map_err(|error| format!("Failed to read primary monitor: {}", error))?,
+        };
+        let monitor = match monitor {
+            Some(monitor) => monitor,
+            None => app
+                .available_monitors()
+                .map_err(|error| format!("Failed to list monitors: {}", error))?
+                .into_iter()
+                .next()
+                .ok_or_else(|| "No monitors found.".to_string())?,
+        };
+        Ok(MonitorBounds::from(&monitor))
+    }
+}
+
+/// Navigate the command bar window to another in-app `/desktop/...` route
+/// (e.g. a quick-note mode) without recreating it. Anything that would leave
+/// the app's origin is rejected. No-ops when the command bar doesn't exist.
+#[tauri::command]
+fn set_command_bar_route(app: tauri::AppHandle, route: String) -> Result<(), String> {
+    let route = route.trim();
+    if !route.starts_with("/desktop/") || route.contains('\\') {
+        return Err(format!(
+            "Unsupported command bar route '{}'. Expected a path under /desktop/.",
+            route
+        ));
+    }
+
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
+            return Ok(());
+        };
+        let current_url = command_bar_window
+            .url()
+            .map_err(|error| format!("Failed to read command bar URL: {}", error))?;
+        let next_url = current_url
+            .join(route)
+            .map_err(|error| format!("Failed to resolve command bar route: {}", error))?;
+        // `join` resolves `..` segments, so check the result, not just the input.
+        if next_url.origin() != current_url.origin() || !next_url.path().starts_with("/desktop/") {
+            return Err(format!(
+                "Unsupported command bar route '{}'. Expected a path under /desktop/.",
+                route
+            ));
+        }
+        command_bar_window
+            .navigate(next_url)
+            .map_err(|error| format!("Failed to navigate command bar: {}", error))
+    }
+}
+
+/// Focus the command bar window and its search input, e.g. from the page's
+/// mount handler. No-ops when the command bar window doesn't exist.
+#[tauri::command]
+fn focus_command_bar_input(app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
+            return Ok(());
+        };
+        command_bar_window
+            .set_focus()
+            .map_err(|error| format!("Failed to focus command bar window: {}", error))?;
+        emit_command_bar_focus_input(&command_bar_window);
+    }
+
+    Ok(())
+}
+
+/// Snap the command bar back to its default placement, forgetting any
+/// position saved after a drag. A chosen `"top"`/`"center"` setting is kept.
+#[tauri::command]
+fn center_command_bar(app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        if let CommandBarPosition::Saved { .. } = stored_command_bar_position(&app) {
+            let store = settings_store(&app)?;
+            store.delete(COMMAND_BAR_POSITION_STORE_KEY);
+            store
+                .save()
+                .map_err(|error| format!("Failed to clear command bar position: {}", error))?;
+        }
+
+        if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+            position_command_bar_on_active_monitor(&app, &command_bar_window)
+                .map_err(|error| format!("Failed to center command bar window: {}", error))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Show or remove the tray icon and remember the choice across launches.
+#[tauri::command]
+fn set_tray_enabled(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let store = settings_store(&app)?;
+    store.set(TRAY_ENABLED_STORE_KEY, enabled);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save tray setting: {}", error))?;
+
+    #[cfg(desktop)]
+    {
+        if enabled {
+            create_tray_icon(&app)
+                .map_err(|error| format!("Failed to create tray icon: {}", error))?;
+        } else {
+            let _ = app.remove_tray_by_id(TRAY_ICON_ID);
+        }
+    }
+
+    Ok(())
+}
+
+/// Register or unregister Kompose as a login item (LaunchAgent on macOS,
+/// registry Run key on Windows, autostart `.desktop` entry on Linux).
+#[tauri::command]
+fn set_autostart_enabled(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    #[cfg(desktop)]
+    {
+        let autolaunch = app.autolaunch();
+        let result = if enabled {
+            autolaunch.enable()
+        } else {
+            autolaunch.disable()
+        };
+        result.map_err(|error| format!("Failed to update launch at login: {}", error))?;
+    }
+
+    let store = settings_store(&app)?;
+    store.set(AUTOSTART_ENABLED_STORE_KEY, enabled);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save launch at login setting: {}", error))
+}
+
+/// Whether Kompose is actually registered to launch at login. Reads the OS
+/// state rather than the stored preference, and re-syncs the store from it.
+#[tauri::command]
+fn get_autostart_enabled(app: tauri::AppHandle) -> Result<bool, String> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        return Ok(false);
+    }
+
+    #[cfg(desktop)]
+    {
+        let enabled = app
+            .autolaunch()
+            .is_enabled()
+            .map_err(|error| format!("Failed to read launch at login state: {}", error))?;
+        if let Ok(store) = settings_store(&app) {
+            store.set(AUTOSTART_ENABLED_STORE_KEY, enabled);
+        }
+        Ok(enabled)
+    }
+}
+
+/// Show or hide the Dock icon (macOS only) and remember the choice. A no-op
+/// elsewhere beyond persisting the preference.
+#[tauri::command]
+fn set_dock_icon_visible(app: tauri::AppHandle, visible: bool) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    set_dock_icon_visible_native(visible);
+
+    let store = settings_store(&app)?;
+    store.set(DOCK_ICON_VISIBLE_STORE_KEY, visible);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save Dock icon setting: {}", error))
+}
+
+/// Whether Kompose has macOS Accessibility permission. Always `true` on other
+/// platforms so the frontend can call it unconditionally.
+#[tauri::command]
+fn accessibility_permission_status() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        is_accessibility_trusted(false)
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        true
+    }
+}
+
+/// Ask for Accessibility permission: shows the system prompt and, since macOS
+/// only prompts once, also opens the Privacy & Security pane while access is
+/// still missing. Returns the current trust state; a no-op off macOS.
+#[tauri::command]
+fn request_accessibility_permission(app: tauri::AppHandle) -> Result<bool, String> {
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = app;
+        Ok(true)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if is_accessibility_trusted(true) {
+            return Ok(true);
+        }
+        app.opener()
+            .open_url(ACCESSIBILITY_SETTINGS_URL, None::<&str>)
+            .map_err(|error| format!("Failed to open Accessibility settings: {}", error))?;
+        Ok(false)
+    }
+}
+
+/// The app that was frontmost when the command bar opened, or `None` if it
+/// was Kompose itself or isn't known (always `None` off macOS).
+#[tauri::command]
+fn get_previous_app(state: tauri::State<CommandBarShortcutState>) -> Option<AppContext> {
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = state;
+        None
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let pid = *lock_recovering(&state.previous_frontmost_pid, PREVIOUS_APP_STATE);
+        if pid <= 0 || pid == std::process::id() as i32 {
+            return None;
+        }
+        let name = lock_recovering(&state.previous_frontmost_app_name, PREVIOUS_APP_STATE).clone();
+        Some(AppContext { name, pid })
+    }
+}
+
+/// Regular running apps for the command bar's "switch to app" source.
+/// Empty off macOS for now.
+#[tauri::command]
+fn list_running_apps() -> Vec<RunningApp> {
+    #[cfg(target_os = "macos")]
+    {
+        get_running_apps()
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Switch to the app with `pid` and dismiss the command bar, or refocus it
+/// when `commandBar.closeAfterAction` is off.
+#[tauri::command]
+fn activate_app(app: tauri::AppHandle, pid: i32) -> Result<(), String> {
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = app;
+        let _ = pid;
+        Err("Switching apps is only supported on macOS.".to_string())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if !activate_app_by_pid(pid) {
+            return Err(format!("Failed to activate app with PID {}.", pid));
+        }
+        if !stored_close_after_action(&app) {
+            refocus_command_bar_input(&app);
+            return Ok(());
+        }
+        // The target app is already frontmost, so hiding the bar can't hand
+        // focus to the main window.
+        if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+            command_bar_window
+                .hide()
+                .map_err(|error| format!("Failed to hide command bar window: {}", error))?;
+        }
+        command_bar_visibility_changed(&app, false);
+        Ok(())
+    }
+}
+
+/// Reactivate the app that was frontmost before the command bar opened and
+/// press `modifiers` + `key` in it, e.g. `(["super"], "C")` to copy its
+/// selection before reading the clipboard. Needs Accessibility permission
+/// (see `accessibility_permission_status`). macOS only.
+#[tauri::command]
+fn send_keystroke_to_previous_app(
+    app: tauri::AppHandle,
+    modifiers: Vec<String>,
+    key: String,
+) -> Result<(), String> {
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = app;
+        let _ = modifiers;
+        let _ = key;
+        Err("Sending keystrokes is only supported on macOS.".to_string())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let mut mods = Modifiers::empty();
+        for token in &modifiers {
+            mods |= parse_modifier_token(token).ok_or_else(|| {
+                format!(
+                    "Unknown keystroke modifier '{}'. Expected super, ctrl, alt, or shift.",
+                    token
+                )
+            })?;
+        }
+        let virtual_key = parse_key_code(&key)
+            .and_then(macos_virtual_key)
+            .ok_or_else(|| format!("Unsupported keystroke key '{}'.", key))?;
+
+        if !is_accessibility_trusted(false) {
+            return Err(
+                "Kompose needs Accessibility permission to send keystrokes. Grant it in System Settings > Privacy & Security > Accessibility."
+                    .to_string(),
+            );
+        }
+
+        let state = app.state::<CommandBarShortcutState>();
+        let pid = *lock_recovering(&state.previous_frontmost_pid, PREVIOUS_APP_STATE);
+        if pid <= 0 || pid == std::process::id() as i32 {
+            return Err("There is no previous app to send the keystroke to.".to_string());
+        }
+        if !activate_app_by_pid(pid) {
+            return Err(format!("Failed to activate app with PID {}.", pid));
+        }
+        let close_after_action = stored_close_after_action(&app);
+        if close_after_action {
+            if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+                command_bar_window
+                    .hide()
+                    .map_err(|error| format!("Failed to hide command bar window: {}", error))?;
+            }
+            command_bar_visibility_changed(&app, false);
+        }
+
+        // Activation completes asynchronously; give the app a moment to become
+        // key before posting, off the main thread so the UI doesn't stall.
+        // A bar kept open takes focus back only once the keystroke has landed.
+        std::thread::spawn(move || {
+            std::thread::sleep(KEYSTROKE_ACTIVATION_DELAY);
+            post_keystroke(virtual_key, mods);
+            if !close_after_action {
+                refocus_command_bar_input(&app);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Text on the clipboard, or an empty string when it's empty or holds
+/// something other than text (e.g. an image).
+#[tauri::command]
+fn read_clipboard_text(app: tauri::AppHandle) -> Result<String, String> {
+    match app.clipboard().read_text() {
+        Ok(text) => Ok(text),
+        Err(error) => {
+            log::info!("Clipboard has no text to read: {}", error);
+            Ok(String::new())
+        }
+    }
+}
+
+/// Put `text` on the clipboard, e.g. so a command bar action can dismiss and
+/// let the user paste into the previous app.
+#[tauri::command]
+fn write_clipboard_text(app: tauri::AppHandle, text: String) -> Result<(), String> {
+    app.clipboard()
+        .write_text(text)
+        .map_err(|error| format!("Failed to write to clipboard: {}", error))
+}
+
+/// Installed apps ranked by fuzzy match against `query`, best first, for the
+/// command bar's launcher source. Scans on a blocking thread so the UI
+/// thread never waits on the filesystem.
+#[tauri::command]
+async fn native_search(query: String, limit: usize) -> Vec<SearchResult> {
+    let query = query.trim().to_string();
+    if query.is_empty() || limit == 0 {
+        return Vec::new();
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut ranked: Vec<(i64, SearchResult)> = installed_apps()
+            .into_iter()
+            .filter_map(|result| Some((fuzzy_score(&query, &result.name)?, result)))
+            .collect();
+        ranked.sort_by(|(score, result), (other_score, other)| {
+            other_score
+                .cmp(score)
+                .then_with(|| result.name.cmp(&other.name))
+        });
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(_, result)| result)
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Open an app, file, or folder from a search result, then dismiss the
+/// command bar (see `finish_command_bar_action`). Desktop entries on Linux are launched through `gio` since
+/// opening them would just show the file.
+#[tauri::command]
+fn launch_path(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    let launch_target = PathBuf::from(&path);
+    if !launch_target.exists() {
+        return Err(format!("'{}' does not exist.", path));
+    }
+
+    #[cfg(target_os = "linux")]
+    let launched_desktop_entry = if launch_target
+        .extension()
+        .is_some_and(|ext| ext == "desktop")
+    {
+        std::process::Command::new("gio")
+            .arg("launch")
+            .arg(&launch_target)
+            .spawn()
+            .map_err(|error| format!("Failed to launch '{}': {}", path, error))?;
+        true
+    } else {
+        false
+    };
+    #[cfg(not(target_os = "linux"))]
+    let launched_desktop_entry = false;
+
+    if !launched_desktop_entry {
+        app.opener()
+            .open_path(path.as_str(), None::<&str>)
+            .map_err(|error| format!("Failed to open '{}': {}", path, error))?;
+    }
+
+    #[cfg(desktop)]
+    finish_command_bar_action(&app);
+    Ok(())
+}
+
+/// Show a search result in the platform file manager with it selected
+/// (Finder, Explorer, or the Linux file manager), without opening it.
+#[tauri::command]
+fn reveal_in_file_manager(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    let reveal_target = PathBuf::from(&path);
+    if !reveal_target.exists() {
+        return Err(format!("'{}' does not exist.", path));
+    }
+    app.opener()
+        .reveal_item_in_dir(&reveal_target)
+        .map_err(|error| format!("Failed to reveal '{}': {}", path, error))
+}
+
+/// Open a URL in the user's default handler. All frontend URL opening should
+/// go through here so command bar results can't launch arbitrary schemes.
+#[tauri::command]
+fn open_external(app: tauri::AppHandle, url: String) -> Result<(), String> {
+    let parsed = tauri::Url::parse(url.trim())
+        .map_err(|error| format!("Invalid URL '{}': {}", url, error))?;
+    if !OPEN_EXTERNAL_ALLOWED_SCHEMES.contains(&parsed.scheme()) {
+        return Err(format!(
+            "Refusing to open URL with unsupported scheme '{}'.",
+            parsed.scheme()
+        ));
+    }
+    app.opener()
+        .open_url(parsed.as_str(), None::<&str>)
+        .map_err(|error| format!("Failed to open URL: {}", error))
+}
+
+/// Version and build details for the About panel and bug reports.
+#[tauri::command]
+fn app_version(app: tauri::AppHandle) -> VersionInfo {
+    VersionInfo {
+        version: app.package_info().version.to_string(),
+        commit: option_env!("KOMPOSE_GIT_COMMIT"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+    }
+}
+
+/// Everything support usually asks for in one call: version, shortcut
+/// registration, deep link and autostart state, Accessibility permission,
+/// and the command bar window. Never fails as a whole. Error text passes
+/// through `redact_secrets`, as in exported logs, since it's meant to be
+/// pasted into bug reports.
+#[tauri::command]
+fn diagnostics(app: tauri::AppHandle, state: tauri::State<CommandBarShortcutState>) -> Diagnostics {
+    let mut errors = Vec::new();
+    let shortcut = command_bar_shortcut_status(state)
+        .map_err(|error| errors.push(format!("shortcut: {}", error)))
+        .ok()
+        .map(|mut status| {
+            status.error = status.error.map(|error| redact_secrets(&error));
+            status
+        });
+    let deep_link_registered = is_deep_link_registered(app.clone())
+        .map_err(|error| errors.push(format!("deep link: {}", error)))
+        .ok();
+    let autostart_enabled = get_autostart_enabled(app.clone())
+        .map_err(|error| errors.push(format!("autostart: {}", error)))
+        .ok();
+
+    #[cfg(desktop)]
+    let command_bar_visible = is_command_bar_visible(&app);
+    #[cfg(not(desktop))]
+    let command_bar_visible = false;
+
+    Diagnostics {
+        version: app_version(app.clone()),
+        shortcut,
+        deep_link_registered,
+        autostart_enabled,
+        accessibility_trusted: accessibility_permission_status(),
+        command_bar_exists: app.get_webview_window(COMMAND_BAR_WINDOW_LABEL).is_some(),
+        command_bar_visible,
+        errors: errors.iter().map(|error| redact_secrets(error)).collect(),
+    }
+}
+
+/// Show a native notification, e.g. when a background task finishes while
+/// the main window is hidden. No-ops when notifications are turned off.
+#[tauri::command]
+fn notify(app: tauri::AppHandle, title: String, body: String) -> Result<(), String> {
+    show_notification(&app, &title, &body)
+}
+
+/// `"granted"`, `"denied"`, or `"prompt"` when the user hasn't been asked.
+#[tauri::command]
+fn notification_permission_status(app: tauri::AppHandle) -> Result<String, String> {
+    let state = app
+        .notification()
+        .permission_state()
+        .map_err(|error| format!("Failed to read notification permission: {}", error))?;
+    Ok(permission_state_name(state).to_string())
+}
+
+/// Ask for notification permission (macOS shows its prompt the first time)
+/// and return the resulting status.
+#[tauri::command]
+fn request_notification_permission(app: tauri::AppHandle) -> Result<String, String> {
+    let state = app
+        .notification()
+        .request_permission()
+        .map_err(|error| format!("Failed to request notification permission: {}", error))?;
+    Ok(permission_state_name(state).to_string())
+}
+
+/// Current OS appearance (`"dark"`/`"light"`), so the frontend can match it
+/// on first paint before any `theme://changed` event arrives.
+#[tauri::command]
+fn get_system_theme(window: tauri::WebviewWindow) -> Result<String, String> {
+    let theme = window
+        .theme()
+        .map_err(|error| format!("Failed to read system theme: {}", error))?;
+    Ok(theme_name(theme).to_string())
+}
+
+/// Quit Kompose after releasing its global shortcuts.
+#[tauri::command]
+fn quit_app(app: tauri::AppHandle) {
+    flush_settings(&app);
+    #[cfg(desktop)]
+    unregister_all_shortcuts(&app);
+    app.exit(0);
+}
+
+/// Restart Kompose to apply settings that need a fresh launch. Settings are
+/// flushed to disk and global shortcuts released first so the new process
+/// starts from the saved state and can register them again.
+#[tauri::command]
+fn relaunch_app(app: tauri::AppHandle) -> Result<(), String> {
+    // A full save covers anything `schedule_settings_save` was holding back.
+    if let Ok(mut guard) = app.state::<SettingsSaveState>().last_write.lock() {
+        *guard = None;
+    }
+    settings_store(&app)?
+        .save()
+        .map_err(|error| format!("Failed to save settings before relaunch: {}", error))?;
+    #[cfg(desktop)]
+    unregister_all_shortcuts(&app);
+    app.restart();
+}
+
+/// Size the command bar for its next open only; later opens return to the
+/// default size.
+#[tauri::command]
+fn set_next_open_size(
+    state: tauri::State<CommandBarShortcutState>,
+    width: f64,
+    height: f64,
+) -> Result<(), String> {
+    let size = clamp_command_bar_size(width, height)?;
+    *lock_recovering(&state.next_open_size, OPEN_SIZE_STATE) = Some(size);
+    Ok(())
+}
+
+/// Reveal the log directory in the platform file manager so users can attach
+/// logs when filing issues.
+#[tauri::command]
+fn open_log_directory(app: tauri::AppHandle) -> Result<(), String> {
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|error| format!("Failed to resolve log directory: {}", error))?;
+    std::fs::create_dir_all(&log_dir)
+        .map_err(|error| format!("Failed to create log directory: {}", error))?;
+    app.opener()
+        .open_path(log_dir.to_string_lossy(), None::<&str>)
+        .map_err(|error| format!("Failed to open log directory: {}", error))
+}
+
+/// Path of the app data directory (settings and auth stores), which differs
+/// per OS, so support can point users at it.
+#[tauri::command]
+fn app_data_dir(app: tauri::AppHandle) -> Result<String, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| format!("Failed to resolve app data directory: {}", error))?;
+    Ok(data_dir.to_string_lossy().into_owned())
+}
+
+/// Reveal the app data directory in the platform file manager, e.g. so a
+/// user can zip it up for a support request.
+#[tauri::command]
+fn open_app_data_dir(app: tauri::AppHandle) -> Result<(), String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|error| format!("Failed to resolve app data directory: {}", error))?;
+    std::fs::create_dir_all(&data_dir)
+        .map_err(|error| format!("Failed to create app data directory: {}", error))?;
+    app.opener()
+        .open_path(data_dir.to_string_lossy(), None::<&str>)
+        .map_err(|error| format!("Failed to open app data directory: {}", error))
+}
+
+/// Return the current log file with secrets redacted. With `save_to_file`
+/// a native save dialog asks where to also write it; cancelling skips the
+/// write. Returns `None` when no log file has been written yet. Async so the
+/// dialog doesn't block the main thread.
+#[tauri::command]
+async fn export_logs(app: tauri::AppHandle, save_to_file: bool) -> Result<Option<String>, String> {
+    let Some(log_file) = current_log_file(&app)? else {
+        return Ok(None);
+    };
+
+    let raw = std::fs::read(&log_file).map_err(|error| {
+        format!(
+            "Failed to read log file '{}': {}",
+            log_file.display(),
+            error
+        )
+    })?;
+    let contents = redact_secrets(&String::from_utf8_lossy(&raw));
+
+    if save_to_file {
+        use tauri_plugin_dialog::DialogExt;
+        let destination = app
+            .dialog()
+            .file()
+            .set_title("Export Kompose Logs")
+            .set_file_name(EXPORTED_LOG_FILE_NAME)
+            .add_filter("Log", &["log"])
+            .blocking_save_file();
+        if let Some(destination) = destination {
+            let destination = destination
+                .into_path()
+                .map_err(|error| format!("Failed to resolve export location: {}", error))?;
+            std::fs::write(&destination, &contents).map_err(|error| {
+                format!(
+                    "Failed to export logs to '{}': {}",
+                    destination.display(),
+                    error
+                )
+            })?;
+        }
+    }
+
+    Ok(Some(contents))
+}
+
+#[cfg(desktop)]
+#[tauri::command]
+fn get_primary_modifier() -> PrimaryModifierInfo {
+    primary_modifier_info()
+}
+
+/// Built-in command bar presets with their platform-resolved bindings, so the
+/// settings UI doesn't keep its own copy of the list, followed by any active
+/// `custom:` bindings.
+#[cfg(desktop)]
+#[tauri::command]
+fn list_shortcut_presets(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+) -> Vec<ShortcutPreset> {
+    let custom_preset_ids: Vec<String> = state
+        .lock_active_presets()
+        .iter()
+        .filter(|preset_id| preset_id.starts_with(CUSTOM_SHORTCUT_PRESET_PREFIX))
+        .cloned()
+        .collect();
+
+    let built_in = SHORTCUT_PRESETS.iter().filter_map(|&(id, label)| {
+        let shortcut = shortcut_for_preset(id)?;
+        Some(ShortcutPreset {
+            id: id.to_string(),
+            label: label.to_string(),
+            shortcut: shortcut_display(&shortcut),
+            registered: app.global_shortcut().is_registered(shortcut),
+        })
+    });
+    let custom = custom_preset_ids.into_iter().filter_map(|id| {
+        let shortcut = shortcut_for_preset(&id)?;
+        let display = shortcut_display(&shortcut);
+        Some(ShortcutPreset {
+            id,
+            label: display.clone(),
+            shortcut: display,
+            registered: app.global_shortcut().is_registered(shortcut),
+        })
+    });
+    built_in.chain(custom).collect()
+}
+
+/// `"cmd"` on macOS, `"ctrl"` elsewhere: the modifier `cmd_or_ctrl_*`
+/// presets actually bind, for preset descriptions in the settings UI.
+#[cfg(desktop)]
+#[tauri::command]
+fn primary_modifier_label() -> String {
+    primary_modifier_info().label.to_ascii_lowercase()
+}
+
+/// Persist the background update-check interval (0 disables it) and
+/// reschedule the running check loop.
+#[tauri::command]
+fn set_update_check_interval(
+    app: tauri::AppHandle,
+    state: tauri::State<UpdateScheduleState>,
+    hours: u64,
+) -> Result<(), String> {
+    if hours > MAX_UPDATE_CHECK_INTERVAL_HOURS {
+        return Err(format!(
+            "Update check interval must be between 0 and {} hours.",
+            MAX_UPDATE_CHECK_INTERVAL_HOURS
+        ));
+    }
+
+    let store = settings_store(&app)?;
+    store.set(UPDATE_CHECK_INTERVAL_STORE_KEY, hours);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save update check interval: {}", error))?;
+
+    let guard = lock_recovering(&state.interval_sender, UPDATE_SCHEDULE_STATE);
+    if let Some(sender) = guard.as_ref() {
+        sender
+            .send(hours)
+            .map_err(|_| "Update check scheduler is not running.".to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Put the command bar back to its factory size and placement, dropping any
+/// pending one-shot size, saved width, saved position, and anchor. Shortcut
+/// and other settings are left alone.
+#[tauri::command]
+fn reset_command_bar_geometry(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+) -> Result<(), String> {
+    *lock_recovering(&state.next_open_size, OPEN_SIZE_STATE) = None;
+    *lock_recovering(&state.open_size_overridden, OPEN_SIZE_STATE) = false;
+
+    let store = settings_store(&app)?;
+    store.delete(COMMAND_BAR_POSITION_STORE_KEY);
+    store.delete(COMMAND_BAR_ANCHOR_STORE_KEY);
+    store.delete(COMMAND_BAR_WIDTH_STORE_KEY);
+    store.delete(COMMAND_BAR_HEIGHT_STORE_KEY);
+    store
+        .save()
+        .map_err(|error| format!("Failed to clear command bar geometry: {}", error))?;
+
+    #[cfg(desktop)]
+    if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        command_bar_window
+            .set_size(tauri::LogicalSize::new(
+                COMMAND_BAR_DEFAULT_WIDTH,
+                COMMAND_BAR_DEFAULT_HEIGHT,
+            ))
+            .map_err(|error| format!("Failed to resize command bar window: {}", error))?;
+        position_command_bar_on_active_monitor(&app, &command_bar_window)
+            .map_err(|error| format!("Failed to position command bar window: {}", error))?;
+    }
+
+    if let Err(error) = app.emit("command-bar://geometry-reset", ()) {
+        log::warn!("Failed to emit command bar geometry reset event: {}", error);
+    }
+
+    Ok(())
+}
+
+/// Whether onboarding has been finished.
+fn stored_onboarding_completed(app: &tauri::AppHandle) -> bool {
+    settings_store(app)
+        .ok()
+        .and_then(|store| store.get(ONBOARDING_COMPLETED_STORE_KEY))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+/// Write the onboarding flag on the first launch that knows about it:
+/// installs from before onboarding existed count as finished, a fresh install
+/// doesn't. An upgrade is recognised by any stored setting or an existing
+/// auth store, since users who kept the default shortcut never saved one.
+/// Writing `false` explicitly keeps a shortcut chosen mid-onboarding from
+/// later looking like an upgrade.
+fn migrate_onboarding_flag(app: &tauri::AppHandle) -> Result<(), String> {
+    let store = settings_store(app)?;
+    if store.has(ONBOARDING_COMPLETED_STORE_KEY) {
+        return Ok(());
+    }
+    let auth_store_exists = app
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join(AUTH_STORE_FILE).exists())
+        .unwrap_or(false);
+    let upgraded = !store.is_empty() || auth_store_exists;
+    store.set(ONBOARDING_COMPLETED_STORE_KEY, upgraded);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save onboarding state: {}", error))
+}
+
+/// Whether the onboarding flow still needs to run. Until it completes, the
+/// command bar shortcut isn't registered at launch so onboarding can pick one.
+#[tauri::command]
+fn is_first_run(app: tauri::AppHandle) -> bool {
+    !stored_onboarding_completed(&app)
+}
+
+/// Record that onboarding finished, saving right away so a relaunch doesn't
+/// replay it, and register the command bar shortcut that launch skipped.
+#[tauri::command]
+fn mark_onboarding_complete(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+) -> Result<(), KomposeError> {
+    let store = settings_store(&app)?;
+    store.set(ONBOARDING_COMPLETED_STORE_KEY, true);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save onboarding state: {}", error))?;
+
+    #[cfg(not(desktop))]
+    let _ = state;
+
+    // Registers the active presets unless onboarding already bound them.
+    #[cfg(desktop)]
+    {
+        let preset_ids = state.lock_active_presets().clone();
+        let preset_ids = switch_shortcut_presets(&app, &state, &preset_ids)?;
+        persist_shortcut_presets(&app, &preset_ids)?;
+    }
+
+    Ok(())
+}
+
+/// Wipe every stored desktop setting and return to a first-run state: the
+/// default shortcut is re-registered, launch at login is turned off, the tray
+/// and Dock icon come back, and the command bar is torn down so it's rebuilt
+/// with default geometry. Emits `settings://reset` so the UI can refresh.
+#[tauri::command]
+fn reset_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+) -> Result<(), String> {
+    #[cfg(desktop)]
+    {
+        let enabled = *state.lock_enabled();
+        let preset_ids = state.lock_active_presets().clone();
+        if enabled {
+            for preset_id in &preset_ids {
+                unregister_shortcut_preset(&app, preset_id);
+            }
+        }
+    }
+
+    // Resetting settings doesn't replay onboarding; once it's done the
+    // default shortcut is registered right away below.
+    let onboarding_completed = stored_onboarding_completed(&app);
+    let store = settings_store(&app)?;
+    store.clear();
+    store.set(ONBOARDING_COMPLETED_STORE_KEY, onboarding_completed);
+    store
+        .save()
+        .map_err(|error| format!("Failed to save reset settings: {}", error))?;
+    state.reset_to_defaults();
+
+    #[cfg(desktop)]
+    {
+        // Before onboarding the default preset is only made active; finishing
+        // onboarding registers it.
+        if onboarding_completed {
+            let result = register_shortcut_preset(&app, DEFAULT_SHORTCUT_PRESET_ID);
+            record_shortcut_registration(&state, &result);
+            if let Err(error) = result {
+                log::warn!("Failed to register default command bar shortcut: {}", error);
+            }
+        }
+
+        if app.autolaunch().is_enabled().unwrap_or(false) {
+            if let Err(error) = app.autolaunch().disable() {
+                log::warn!("Failed to disable launch at login: {}", error);
+            }
+        }
+        if let Err(error) = create_tray_icon(&app) {
+            log::warn!("Failed to create tray icon: {}", error);
+        }
+        if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+            if let Err(error) = command_bar_window.destroy() {
+                log::warn!("Failed to destroy command bar window: {}", error);
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    set_dock_icon_visible_native(true);
+
+    let schedule_state = app.state::<UpdateScheduleState>();
+    if let Some(sender) =
+        lock_recovering(&schedule_state.interval_sender, UPDATE_SCHEDULE_STATE).as_ref()
+    {
+        let _ = sender.send(DEFAULT_UPDATE_CHECK_INTERVAL_HOURS);
+    }
+
+    if let Err(error) = app.emit("settings://reset", ()) {
+        log::warn!("Failed to emit settings reset event: {}", error);
+    }
+
+    Ok(())
+}
+
+/// Re-read the settings file after it changed outside the app (hand edits,
+/// sync from another machine) and apply it live: the command bar shortcut
+/// and the open command bar's size, opacity, window level, and capture
+/// setting. Nothing is re-registered or resized when it didn't change, and
+/// position is picked up on the next open. Unsaved debounced writes are
+/// dropped since the file on disk wins. Emits `settings://reloaded`.
+#[tauri::command]
+fn reload_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<CommandBarShortcutState>,
+) -> Result<(), KomposeError> {
+    if let Ok(mut guard) = app.state::<SettingsSaveState>().last_write.lock() {
+        *guard = None;
+    }
+    settings_store(&app)?
+        .reload()
+        .map_err(|error| format!("Failed to reload settings: {}", error))?;
+
+    #[cfg(not(desktop))]
+    let _ = state;
+
+    #[cfg(desktop)]
+    {
+        // Until onboarding completes the shortcut stays unregistered, as at launch.
+        if stored_onboarding_completed(&app) {
+            let preset_ids = stored_shortcut_presets(&app);
+            let enabled = stored_shortcut_enabled(&app);
+            let was_enabled = *state.lock_enabled();
+            if was_enabled && !enabled {
+                let active_presets = state.lock_active_presets().clone();
+                for preset_id in &active_presets {
+                    unregister_shortcut_preset(&app, preset_id);
+                }
+                *state.lock_registration() = ShortcutRegistration::default();
+                *state.lock_enabled() = false;
+            }
+            // A no-op when the presets are unchanged and already registered.
+            switch_shortcut_presets(&app, &state, &preset_ids)?;
+            if !was_enabled && enabled {
+                *state.lock_enabled() = true;
+                let result = register_shortcut_presets(&app, &preset_ids);
+                record_shortcut_registration(&state, &result);
+                result?;
+            }
+        }
+
+        if let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+            let scale_factor = command_bar_window
+                .scale_factor()
+                .map_err(|error| format!("Failed to read command bar scale factor: {}", error))?;
+            let size = command_bar_window
+                .inner_size()
+                .map_err(|error| format!("Failed to read command bar size: {}", error))?
+                .to_logical::<f64>(scale_factor);
+            let (width, height) = (
+                stored_command_bar_width(&app),
+                stored_command_bar_height(&app),
+            );
+            if (size.width - width).abs() >= 1.0 || (size.height - height).abs() >= 1.0 {
+                resize_command_bar_keeping_center(&command_bar_window, width, height)
+                    .map_err(|error| format!("Failed to resize command bar window: {}", error))?;
+            }
+
+            apply_command_bar_opacity(&command_bar_window, stored_command_bar_opacity(&app))?;
+
+            let on_top = stored_command_bar_always_on_top(&app);
+            let is_on_top = command_bar_window.is_always_on_top().unwrap_or(!on_top);
+            if is_on_top != on_top {
+                command_bar_window
+                    .set_always_on_top(on_top)
+                    .map_err(|error| {
+                        format!("Failed to update command bar window level: {}", error)
+                    })?;
+                #[cfg(target_os = "macos")]
+                if on_top {
+                    configure_command_bar_for_fullscreen_spaces(&command_bar_window);
+                }
+            }
+
+            command_bar_window
+                .set_content_protected(stored_command_bar_capture_excluded(&app))
+                .map_err(|error| {
+                    format!("Failed to update command bar capture setting: {}", error)
+                })?;
         }
-    });
+    }
+
+    if let Err(error) = app.emit("settings://reloaded", ()) {
+        log::warn!("Failed to emit settings reloaded event: {}", error);
+    }
 
     Ok(())
 }
 
+/// Display string (e.g. `⇧⌘K`) for the primary shortcut that is actually
+/// registered, or a placeholder while the shortcut is disabled or failed to
+/// register (including before onboarding has bound it).
 #[cfg(desktop)]
-fn toggle_command_bar_window(app: &tauri::AppHandle) -> tauri::Result<()> {
-    let Some(command_bar_window) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) else {
-        return Ok(());
-    };
-
-    if command_bar_window.is_visible()? {
-        command_bar_window.hide()?;
-        return Ok(());
+#[tauri::command]
+fn get_active_shortcut_display(
+    state: tauri::State<CommandBarShortcutState>,
+) -> Result<String, KomposeError> {
+    if !*state.lock_enabled() {
+        return Ok(SHORTCUT_DISABLED_DISPLAY.to_string());
+    }
+    if !state.lock_registration().registered {
+        return Ok(SHORTCUT_UNREGISTERED_DISPLAY.to_string());
     }
+    let preset_id = state
+        .lock_active_presets()
+        .first()
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_SHORTCUT_PRESET_ID.to_string());
+    let shortcut = shortcut_for_preset(&preset_id).ok_or_else(|| {
+        KomposeError::ShortcutUnsupported(format!(
+            "Unsupported command bar shortcut preset '{}'.",
+            preset_id
+        ))
+    })?;
+    Ok(shortcut_display(&shortcut))
+}
 
-    // Snapshot the frontmost app before we steal focus so we can
-    // reactivate it when the command bar is dismissed.
-    #[cfg(target_os = "macos")]
+/// Deep-link schemes from `plugins.deep-link.desktop.schemes` in the Tauri
+/// config, which differ between the release and dev flavors.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn configured_deep_link_schemes(app: &tauri::AppHandle) -> Vec<String> {
+    app.config()
+        .plugins
+        .0
+        .get("deep-link")
+        .and_then(|config| config.get("desktop"))
+        .and_then(|desktop| desktop.get("schemes"))
+        .and_then(|schemes| schemes.as_array())
+        .map(|schemes| {
+            schemes
+                .iter()
+                .filter_map(|scheme| scheme.as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether every configured deep-link scheme points at this executable, so
+/// the settings UI can offer a fix when OAuth callbacks won't reach us. A
+/// scheme claimed by another install (e.g. a different dev build path)
+/// counts as unregistered.
+#[tauri::command]
+fn is_deep_link_registered(app: tauri::AppHandle) -> Result<bool, String> {
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     {
-        let pid = get_frontmost_app_pid();
-        if let Ok(mut guard) = app
-            .state::<CommandBarShortcutState>()
-            .previous_frontmost_pid
-            .lock()
-        {
-            *guard = pid;
+        for scheme in configured_deep_link_schemes(&app) {
+            let registered = app.deep_link().is_registered(&scheme).map_err(|error| {
+                format!("Failed to check deep link scheme '{}': {}", scheme, error)
+            })?;
+            if !registered {
+                return Ok(false);
+            }
         }
+        Ok(true)
     }
 
-    command_bar_window.show()?;
-    let _ = command_bar_window.center();
-    command_bar_window.set_focus()?;
-    Ok(())
+    // macOS and mobile register schemes from the app bundle at install time.
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        let _ = app;
+        Ok(true)
+    }
 }
 
+/// (Re)register the configured deep-link schemes to this executable where
+/// that can be done at runtime (Windows, Linux). No-op elsewhere.
 #[tauri::command]
-fn set_command_bar_shortcut_preset(
-    app: tauri::AppHandle,
-    state: tauri::State<CommandBarShortcutState>,
-    preset_id: String,
-) -> Result<(), String> {
-    #[cfg(not(desktop))]
-    {
-        let _ = app;
-        let _ = state;
-        let _ = preset_id;
-        return Ok(());
+fn register_deep_link_scheme(app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    for scheme in configured_deep_link_schemes(&app) {
+        app.deep_link().register(&scheme).map_err(|error| {
+            format!(
+                "Failed to register deep link scheme '{}': {}",
+                scheme, error
+            )
+        })?;
+        log::info!("Registered deep link scheme '{}'.", scheme);
     }
 
-    #[cfg(desktop)]
-    {
-        let next_preset = preset_id.trim().to_string();
-        if shortcut_for_preset(&next_preset).is_none() {
-            return Err(format!(
-                "Unsupported command bar shortcut preset '{}'.",
-                next_preset
-            ));
-        }
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    let _ = app;
 
-        let previous_preset = {
-            let guard = state
-                .active_preset
-                .lock()
-                .map_err(|_| "Failed to lock command bar preset state.".to_string())?;
-            guard.clone()
-        };
+    Ok(())
+}
 
-        if previous_preset == next_preset {
-            return Ok(());
-        }
+/// Start an OAuth sign-in: returns a fresh random `state` for the frontend to
+/// put in the auth URL. Only a callback carrying it gets its token through.
+#[tauri::command]
+fn begin_oauth(state: tauri::State<OAuthState>) -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes)
+        .map_err(|error| format!("Failed to generate OAuth state: {}", error))?;
+    let oauth_state: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
 
-        unregister_shortcut_preset(&app, &previous_preset);
-        register_shortcut_preset(&app, &next_preset)
-            .map_err(|error| format!("Failed to register new shortcut: {}", error))?;
+    *lock_recovering(&state.pending_state, OAUTH_STATE) = Some(oauth_state.clone());
+    Ok(oauth_state)
+}
 
-        {
-            let mut guard = state
-                .active_preset
-                .lock()
-                .map_err(|_| "Failed to lock command bar preset state.".to_string())?;
-            *guard = next_preset;
-        }
+/// Drain deep links and OAuth callbacks buffered since startup. Called once
+/// by the frontend on mount, after it subscribed to the live events, so a
+/// cold-start OAuth callback isn't lost.
+#[tauri::command]
+fn take_pending_deep_links(state: tauri::State<DeepLinkState>) -> PendingDeepLinks {
+    let mut queue = lock_recovering(&state.queue, DEEP_LINK_STATE);
+    queue.frontend_ready = true;
+    PendingDeepLinks {
+        urls: std::mem::take(&mut queue.pending_urls),
+        auth_callbacks: std::mem::take(&mut queue.pending_auth_callbacks),
+    }
+}
+
+/// Check the release feed for a newer version. `Ok(None)` means the app is
+/// up to date; offline and network failures come back as readable errors.
+#[tauri::command]
+async fn check_for_update(app: tauri::AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let probe_app = app.clone();
+    let online = tauri::async_runtime::spawn_blocking(move || current_network_status(&probe_app))
+        .await
+        .unwrap_or(false);
+    if !online {
+        return Err("You appear to be offline. Check your connection and try again.".to_string());
     }
 
+    let update = app
+        .updater()
+        .map_err(|error| format!("Failed to initialize updater: {}", error))?
+        .check()
+        .await
+        .map_err(|error| format!("Failed to check for updates: {}", error))?;
+
+    let info = update.as_ref().map(UpdateInfo::from);
+    let state = app.state::<UpdaterState>();
+    *lock_recovering(&state.pending_update, UPDATER_STATE) = update;
+    Ok(info)
+}
+
+/// Download and install the update found by the last check (checking again
+/// if there isn't one), emitting `updater://progress` as bytes arrive.
+#[tauri::command]
+async fn download_and_install_update(app: tauri::AppHandle) -> Result<(), String> {
+    let pending_update = {
+        let state = app.state::<UpdaterState>();
+        let pending_update = lock_recovering(&state.pending_update, UPDATER_STATE).take();
+        pending_update
+    };
+
+    let update = match pending_update {
+        Some(update) => update,
+        None => app
+            .updater()
+            .map_err(|error| format!("Failed to initialize updater: {}", error))?
+            .check()
+            .await
+            .map_err(|error| format!("Failed to check for updates: {}", error))?
+            .ok_or_else(|| "No update is available.".to_string())?,
+    };
+
+    let version = update.version.clone();
+    let mut downloaded: u64 = 0;
+    update
+        .download_and_install(
+            |chunk_length, total| {
+                downloaded += chunk_length as u64;
+                let progress = UpdateProgress { downloaded, total };
+                if let Err(error) = app.emit("updater://progress", progress) {
+                    log::warn!("Failed to emit update progress event: {}", error);
+                }
+            },
+            || {},
+        )
+        .await
+        .map_err(|error| format!("Failed to download and install update: {}", error))?;
+
+    if let Err(error) = show_notification(
+        &app,
+        "Update installed",
+        &format!("Kompose {} is ready. Restart to finish updating.", version),
+    ) {
+        log::warn!("{}", error);
+    }
     Ok(())
 }
 
@@ -254,44 +5554,197 @@ fn set_command_bar_shortcut_preset(
 /// activates the previous app — no flicker. The command bar is then
 /// marked hidden in Tauri so it stays hidden when the user returns to
 /// Kompose (the main window reappears normally on dock click / Cmd+Tab).
+/// When every window of the previous app is minimized, it is also activated
+/// by PID so the user isn't left looking at the desktop; if it has quit
+/// since, only the command bar is hidden.
+///
+/// On Windows `hide_app_windows` does the equivalent: the previously
+/// foreground window is brought forward before the command bar hides and
+/// the main window is minimized, so activation never falls through to it.
+/// Linux restores focus on X11 via `_NET_ACTIVE_WINDOW`; Wayland doesn't
+/// allow it, so there the command bar is just hidden.
 ///
 /// If the previous app was Kompose itself, we just hide the command bar
 /// and let the main window keep focus.
-#[tauri::command]
-fn dismiss_command_bar(app: tauri::AppHandle) {
-    #[cfg(desktop)]
+#[cfg(desktop)]
+fn dismiss_command_bar_window(app: &tauri::AppHandle) {
+    #[cfg(target_os = "macos")]
     {
-        #[cfg(target_os = "macos")]
-        {
-            let stored_pid = app
-                .state::<CommandBarShortcutState>()
-                .previous_frontmost_pid
-                .lock()
-                .map(|v| *v)
-                .unwrap_or(-1);
+        let state = app.state::<CommandBarShortcutState>();
+        let stored_pid = *lock_recovering(&state.previous_frontmost_pid, PREVIOUS_APP_STATE);
 
-            let our_pid = std::process::id() as i32;
+        let our_pid = std::process::id() as i32;
 
-            if stored_pid > 0 && stored_pid != our_pid {
+        if stored_pid > 0 && stored_pid != our_pid {
+            if is_app_running(stored_pid) {
                 // Atomic hide + activate previous app — no flicker.
                 hide_app();
+                // `hide:` hands focus back but doesn't bring a minimized
+                // app's windows back, so ask for it explicitly.
+                if !app_has_on_screen_windows(stored_pid) && !activate_app_by_pid(stored_pid) {
+                    log::warn!("Failed to activate previous app (pid {}).", stored_pid);
+                }
                 // Mark command bar as hidden so it doesn't reappear on unhide.
                 if let Some(win) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
                     let _ = win.hide();
                 }
+                command_bar_visibility_changed(app, false);
                 return;
             }
+            log::info!(
+                "Previous app (pid {}) is no longer running; hiding command bar only.",
+                stored_pid
+            );
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let state = app.state::<CommandBarShortcutState>();
+        let stored_hwnd = *lock_recovering(&state.previous_foreground_window, PREVIOUS_APP_STATE);
+
+        if stored_hwnd != 0 && !is_own_window(stored_hwnd) {
+            // Activate first so hiding the (now inactive) command bar
+            // doesn't promote the main window.
+            if !hide_app_windows(app, stored_hwnd) {
+                log::warn!("Failed to reactivate previous foreground window.");
+            }
+            command_bar_visibility_changed(app, false);
+            return;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let state = app.state::<CommandBarShortcutState>();
+        let stored_window = *lock_recovering(&state.previous_active_window, PREVIOUS_APP_STATE);
+
+        if is_wayland_session() {
+            log::info!("Wayland session; hiding command bar without restoring focus.");
+        } else if stored_window != 0 && !is_own_x11_window(stored_window) {
+            log::info!("Restoring focus to previous X11 window before hiding command bar.");
+            if !activate_x11_window(stored_window) {
+                log::warn!("Failed to reactivate previous X11 window.");
+            }
+            if let Some(win) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+                let _ = win.hide();
+            }
+            command_bar_visibility_changed(app, false);
+            return;
         }
+    }
+
+    // Same-app case (or other platforms): just hide the command bar window.
+    if let Some(win) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
+        let _ = win.hide();
+    }
+    command_bar_visibility_changed(app, false);
+}
+
+/// Keep the command bar open when it loses focus, for flows that hand focus
+/// to another app on purpose. Cleared automatically when the bar hides.
+#[tauri::command]
+fn set_command_bar_sticky(
+    state: tauri::State<CommandBarShortcutState>,
+    sticky: bool,
+) -> Result<(), String> {
+    *lock_recovering(&state.sticky, STICKY_STATE) = sticky;
+    Ok(())
+}
+
+/// Show the command bar regardless of its current state, unlike the
+/// shortcut which toggles it.
+#[tauri::command]
+fn open_command_bar(app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(desktop)]
+    show_command_bar(&app).map_err(|error| format!("Failed to open command bar: {}", error))?;
+    #[cfg(not(desktop))]
+    let _ = app;
+    Ok(())
+}
+
+/// Frontend entry point for `dismiss_command_bar_window` (Esc, completed
+/// actions).
+#[tauri::command]
+fn dismiss_command_bar(app: tauri::AppHandle) {
+    #[cfg(desktop)]
+    dismiss_command_bar_window(&app);
+    #[cfg(not(desktop))]
+    let _ = app;
+}
+
+/// Called by the command bar page once it handles Escape itself, releasing the
+/// native Escape fallback so key presses reach the webview again.
+#[tauri::command]
+fn command_bar_frontend_ready(app: tauri::AppHandle, state: tauri::State<CommandBarShortcutState>) {
+    *lock_recovering(&state.frontend_ready, FRONTEND_READY_STATE) = true;
+    #[cfg(desktop)]
+    set_command_bar_escape_enabled(&app, false);
+    #[cfg(not(desktop))]
+    let _ = app;
+}
+
+/// Seed the PID that `dismiss_command_bar` will treat as the previously
+/// frontmost app, so the reactivation path can be driven from scripts
+/// instead of depending on real app switching.
+#[cfg(all(target_os = "macos", feature = "test-commands"))]
+#[tauri::command]
+fn set_previous_frontmost_pid(
+    state: tauri::State<CommandBarShortcutState>,
+    pid: i32,
+) -> Result<(), String> {
+    *lock_recovering(&state.previous_frontmost_pid, PREVIOUS_APP_STATE) = pid;
+    Ok(())
+}
+
+/// Bring the main window forward from anywhere, whether the app was hidden
+/// by `dismiss_command_bar` or the window was just minimized.
+#[tauri::command]
+fn show_main_window(app: tauri::AppHandle) -> Result<(), KomposeError> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        return Ok(());
+    }
 
-        // Same-app case (or non-macOS): just hide the command bar window.
-        if let Some(win) = app.get_webview_window(COMMAND_BAR_WINDOW_LABEL) {
-            let _ = win.hide();
+    #[cfg(desktop)]
+    {
+        if app.get_webview_window(MAIN_WINDOW_LABEL).is_none() {
+            return Err(KomposeError::WindowMissing(MAIN_WINDOW_LABEL));
         }
+        bring_main_window_forward(&app)
+            .map_err(|error| format!("Failed to show main window: {}", error))?;
+    }
+
+    Ok(())
+}
+
+/// Tuck the main window away without quitting. On macOS only that window is
+/// hidden (not the whole app as Cmd+H would), so the command bar, tray, and
+/// global shortcuts keep working.
+#[tauri::command]
+fn hide_main_window(app: tauri::AppHandle) -> Result<(), KomposeError> {
+    #[cfg(not(desktop))]
+    {
+        let _ = app;
+        return Ok(());
+    }
+
+    #[cfg(desktop)]
+    {
+        let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) else {
+            return Err(KomposeError::WindowMissing(MAIN_WINDOW_LABEL));
+        };
+        main_window
+            .hide()
+            .map_err(|error| format!("Failed to hide main window: {}", error))?;
     }
+
+    Ok(())
 }
 
 #[tauri::command]
-fn focus_main_window_for_command_bar_selection(app: tauri::AppHandle) -> Result<(), String> {
+fn focus_main_window_for_command_bar_selection(app: tauri::AppHandle) -> Result<(), KomposeError> {
     #[cfg(not(desktop))]
     {
         let _ = app;
@@ -301,7 +5754,7 @@ fn focus_main_window_for_command_bar_selection(app: tauri::AppHandle) -> Result<
     #[cfg(desktop)]
     {
         let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) else {
-            return Err("Main window is not available.".to_string());
+            return Err(KomposeError::WindowMissing(MAIN_WINDOW_LABEL));
         };
 
         main_window
@@ -318,6 +5771,7 @@ fn focus_main_window_for_command_bar_selection(app: tauri::AppHandle) -> Result<
             command_bar_window
                 .hide()
                 .map_err(|error| format!("Failed to hide command bar window: {}", error))?;
+            command_bar_visibility_changed(&app, false);
         }
     }
 
@@ -326,10 +5780,55 @@ fn focus_main_window_for_command_bar_selection(app: tauri::AppHandle) -> Result<
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let builder = tauri::Builder::default()
+    let builder = tauri::Builder::default();
+
+    // A second launch (double-click, or an OAuth callback delivered as a new
+    // process on Windows/Linux) exits and focuses this instance instead. The
+    // `deep-link` feature replays its URL through `on_open_url`, so it takes
+    // the same buffer/emit path. Must be the first plugin registered.
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+        if let Err(error) = bring_main_window_forward(app) {
+            log::warn!("Failed to focus existing main window: {}", error);
+        }
+    }));
+
+    // Remember the main window's size, position, and maximized state. The
+    // command bar places itself, and visibility is left to `--minimized`.
+    #[cfg(desktop)]
+    let builder = builder.plugin(
+        tauri_plugin_window_state::Builder::default()
+            .with_denylist(&[COMMAND_BAR_WINDOW_LABEL])
+            .with_state_flags(
+                tauri_plugin_window_state::StateFlags::SIZE
+                    | tauri_plugin_window_state::StateFlags::POSITION
+                    | tauri_plugin_window_state::StateFlags::MAXIMIZED,
+            )
+            .build(),
+    );
+
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_autostart::init(
+        tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+        Some(vec![AUTOSTART_MINIMIZED_ARG]),
+    ));
+
+    let builder = builder
         .manage(CommandBarShortcutState::default())
+        .manage(SettingsSaveState::default())
+        .manage(DeepLinkState::default())
+        .manage(OAuthState::default())
+        .manage(NetworkStatusState::default())
+        .manage(UpdateScheduleState::default())
+        .manage(UpdaterState::default())
         // Register kompose:// deep link handler for OAuth callbacks.
         .plugin(tauri_plugin_deep_link::init())
+        // Native save dialog for exporting logs.
+        .plugin(tauri_plugin_dialog::init())
+        // Clipboard access for command bar copy/paste actions.
+        .plugin(tauri_plugin_clipboard_manager::init())
+        // Native notifications for background events (e.g. installed updates).
+        .plugin(tauri_plugin_notification::init())
         // Allow opening external URLs/files in the system handlers.
         .plugin(tauri_plugin_opener::init())
         // Allow the updater flow to relaunch the desktop process after install.
@@ -342,12 +5841,33 @@ pub fn run() {
     #[cfg(desktop)]
     let builder = builder.plugin(
         tauri_plugin_global_shortcut::Builder::new()
-            .with_handler(|app, _shortcut, event| {
+            .with_handler(|app, shortcut, event| {
                 if event.state() != ShortcutState::Pressed {
                     return;
                 }
-                if let Err(error) = toggle_command_bar_window(app) {
-                    log::warn!("Failed to toggle command bar window: {}", error);
+                match shortcut_action(app, shortcut) {
+                    Some(ShortcutAction::ToggleMainWindow) => {
+                        if let Err(error) = toggle_main_window(app) {
+                            log::warn!("Failed to toggle main window: {}", error);
+                        }
+                    }
+                    Some(ShortcutAction::DismissCommandBar) => dismiss_command_bar_window(app),
+                    Some(ShortcutAction::ToggleCommandBar) => {
+                        if !accept_command_bar_toggle(app) {
+                            return;
+                        }
+                        if shortcut_emits_event(app) {
+                            emit_shortcut_triggered(app, shortcut);
+                            return;
+                        }
+                        if let Err(error) = toggle_command_bar_window(app) {
+                            log::warn!("Failed to toggle command bar window: {}", error);
+                        }
+                    }
+                    None => log::warn!(
+                        "Ignoring press of unbound shortcut {}.",
+                        shortcut_display(shortcut)
+                    ),
                 }
             })
             .build(),
@@ -356,41 +5876,532 @@ pub fn run() {
     builder
         .invoke_handler(tauri::generate_handler![
             set_command_bar_shortcut_preset,
+            get_command_bar_shortcut_preset,
+            set_command_bar_shortcut_custom,
+            set_command_bar_shortcut_from_string,
+            set_command_bar_shortcut_enabled,
+            set_command_bar_shortcut_emits_event,
+            add_command_bar_shortcut,
+            remove_command_bar_shortcut,
+            get_command_bar_shortcut_presets,
+            command_bar_shortcut_status,
+            take_pending_deep_links,
+            begin_oauth,
+            is_deep_link_registered,
+            register_deep_link_scheme,
+            open_command_bar,
+            set_command_bar_sticky,
             dismiss_command_bar,
-            focus_main_window_for_command_bar_selection
+            command_bar_frontend_ready,
+            focus_main_window_for_command_bar_selection,
+            show_main_window,
+            hide_main_window,
+            save_shortcut_profile,
+            list_shortcut_profiles,
+            apply_shortcut_profile,
+            record_command_usage,
+            set_history_max_entries,
+            get_recent_commands,
+            get_network_status,
+            set_command_bar_height,
+            set_command_bar_width,
+            save_command_bar_position,
+            center_command_bar,
+            set_command_bar_anchor,
+            focus_command_bar_input,
+            set_command_bar_always_on_top,
+            set_command_bar_opacity,
+            set_command_bar_capture_excluded,
+            set_command_bar_animations_enabled,
+            toggle_command_bar_close_after_action,
+            set_command_bar_route,
+            current_monitor_bounds,
+            set_presentation_mode,
+            set_next_open_size,
+            set_tray_enabled,
+            set_autostart_enabled,
+            get_autostart_enabled,
+            set_dock_icon_visible,
+            accessibility_permission_status,
+            request_accessibility_permission,
+            get_previous_app,
+            list_running_apps,
+            activate_app,
+            send_keystroke_to_previous_app,
+            read_clipboard_text,
+            write_clipboard_text,
+            native_search,
+            launch_path,
+            reveal_in_file_manager,
+            quit_app,
+            relaunch_app,
+            app_version,
+            diagnostics,
+            get_system_theme,
+            notify,
+            notification_permission_status,
+            request_notification_permission,
+            open_external,
+            export_logs,
+            open_log_directory,
+            app_data_dir,
+            open_app_data_dir,
+            set_update_check_interval,
+            set_auto_update_enabled,
+            check_for_update,
+            download_and_install_update,
+            reset_command_bar_geometry,
+            reset_settings,
+            reload_settings,
+            is_first_run,
+            mark_onboarding_complete,
+            #[cfg(desktop)]
+            get_active_shortcut_display,
+            #[cfg(desktop)]
+            get_primary_modifier,
+            #[cfg(desktop)]
+            primary_modifier_label,
+            #[cfg(desktop)]
+            list_shortcut_presets,
+            #[cfg(all(target_os = "macos", feature = "test-commands"))]
+            set_previous_frontmost_pid
         ])
         .setup(|app| {
+            // Always log to a rotating file so release users have something to
+            // attach to bug reports; only dev builds also log to stdout.
+            let mut log_builder = tauri_plugin_log::Builder::default()
+                .clear_targets()
+                .target(tauri_plugin_log::Target::new(
+                    tauri_plugin_log::TargetKind::LogDir { file_name: None },
+                ))
+                .max_file_size(LOG_FILE_MAX_BYTES)
+                .rotation_strategy(tauri_plugin_log::RotationStrategy::KeepSome(LOG_FILES_KEPT));
             if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+                log_builder = log_builder
+                    .target(tauri_plugin_log::Target::new(
+                        tauri_plugin_log::TargetKind::Stdout,
+                    ))
+                    .level(log::LevelFilter::Info);
+            } else {
+                log_builder = log_builder.level(log::LevelFilter::Warn);
             }
+            app.handle().plugin(log_builder.build())?;
 
-            // Log deep link URLs received on startup (e.g. kompose://auth/callback?token=...).
-            // The frontend DeepLinkHandler component listens via the JS API for runtime events.
+            // Buffer deep link URLs received on startup (e.g. kompose://auth/callback?token=...)
+            // until the frontend drains them via take_pending_deep_links.
             if let Some(urls) = app.deep_link().get_current()? {
                 log::info!("App opened via deep link: {:?}", urls);
+                forward_deep_links(
+                    app.handle(),
+                    urls.iter().map(|url| url.to_string()).collect(),
+                );
             }
 
-            // Listen for deep link events while the app is running.
-            app.deep_link().on_open_url(|event| {
-                log::info!("Deep link received: {:?}", event.urls());
+            // Forward deep link events received while the app is running.
+            let deep_link_app = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                let urls: Vec<String> = event.urls().iter().map(|url| url.to_string()).collect();
+                log::info!("Deep link received: {:?}", urls);
+                forward_deep_links(&deep_link_app, urls);
             });
 
+            if let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+                forward_theme_changes(&main_window);
+            }
+
+            // Dev builds point at the same release feed, so only schedule
+            // background checks for release builds (matching the frontend).
+            if !cfg!(debug_assertions) {
+                let interval_hours = stored_update_check_interval_hours(app.handle());
+                let sender = spawn_update_check_scheduler(app.handle().clone(), interval_hours);
+                let schedule_state = app.state::<UpdateScheduleState>();
+                *lock_recovering(&schedule_state.interval_sender, UPDATE_SCHEDULE_STATE) =
+                    Some(sender);
+            }
+
+            #[cfg(target_os = "macos")]
+            {
+                let dock_icon_visible = settings_store(app.handle())
+                    .ok()
+                    .and_then(|store| store.get(DOCK_ICON_VISIBLE_STORE_KEY))
+                    .and_then(|value| value.as_bool())
+                    .unwrap_or(true);
+                if !dock_icon_visible {
+                    set_dock_icon_visible_native(false);
+                }
+            }
+
+            if let Err(error) = migrate_onboarding_flag(app.handle()) {
+                log::warn!("{}", error);
+            }
+
             #[cfg(desktop)]
             {
-                create_command_bar_window(app)?;
-                if let Err(error) =
-                    register_shortcut_preset(app.handle(), DEFAULT_SHORTCUT_PRESET_ID)
-                {
-                    log::warn!("Failed to register default command bar shortcut: {}", error);
+                if std::env::args().any(|arg| arg == AUTOSTART_MINIMIZED_ARG) {
+                    if let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+                        let _ = main_window.hide();
+                    }
+                }
+
+                if let Err(error) = ensure_main_window_on_screen(app.handle()) {
+                    log::warn!("Failed to check main window position: {}", error);
+                }
+                spawn_command_bar_idle_teardown(app.handle().clone());
+                let preset_ids = stored_shortcut_presets(app.handle());
+                let shortcut_enabled = stored_shortcut_enabled(app.handle());
+                let shortcut_state = app.state::<CommandBarShortcutState>();
+                *shortcut_state.lock_active_presets() = preset_ids.clone();
+                *shortcut_state.lock_enabled() = shortcut_enabled;
+                // On first run onboarding picks the shortcut and
+                // `mark_onboarding_complete` registers it.
+                if shortcut_enabled && stored_onboarding_completed(app.handle()) {
+                    let result = register_shortcut_presets(app.handle(), &preset_ids);
+                    record_shortcut_registration(&shortcut_state, &result);
+                    if let Err(error) = result {
+                        log::warn!("Failed to register command bar shortcut: {}", error);
+                    }
+                }
+                match app.global_shortcut().register(main_window_shortcut()) {
+                    Ok(()) => bind_shortcut_action(
+                        app.handle(),
+                        main_window_shortcut(),
+                        ShortcutAction::ToggleMainWindow,
+                    ),
+                    Err(error) => {
+                        log::warn!("Failed to register main window shortcut: {}", error)
+                    }
+                }
+                if stored_tray_enabled(app.handle()) {
+                    if let Err(error) = create_tray_icon(app.handle()) {
+                        log::warn!("Failed to create tray icon: {}", error);
+                    }
                 }
             }
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Write debounced settings and release global hotkey grabs on
+            // every exit path so a stale grab can't outlive the process (seen
+            // on Linux).
+            if let tauri::RunEvent::Exit = event {
+                flush_settings(app);
+                #[cfg(desktop)]
+                unregister_all_shortcuts(app);
+            }
+        });
+}
+
+#[cfg(all(test, desktop))]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Swap the default binding for `ctrl_space` against a fake registry in
+    /// which registering `ctrl_space` fails, as when another app owns it.
+    /// Returns the swap result, the registry afterwards, and the state.
+    fn swap_with_conflict() -> (
+        Result<Vec<String>, KomposeError>,
+        Vec<String>,
+        CommandBarShortcutState,
+    ) {
+        let state = CommandBarShortcutState::default();
+        record_shortcut_registration(&state, &Ok(()));
+        let registry = RefCell::new(vec![DEFAULT_SHORTCUT_PRESET_ID.to_string()]);
+        let result = switch_shortcut_presets_with(
+            &state,
+            &["ctrl_space".to_string()],
+            false,
+            |preset_id| registry.borrow().iter().any(|id| id == preset_id),
+            |preset_id| {
+                if preset_id == "ctrl_space" {
+                    return Err(KomposeError::ShortcutRegistrationFailed(
+                        "Ctrl + Space is in use.".to_string(),
+                    ));
+                }
+                registry.borrow_mut().push(preset_id.to_string());
+                Ok(())
+            },
+            |preset_id| registry.borrow_mut().retain(|id| id != preset_id),
+        );
+        let registry = registry.into_inner();
+        (result, registry, state)
+    }
+
+    #[test]
+    fn switching_to_a_taken_shortcut_fails() {
+        let (result, _, _) = swap_with_conflict();
+        assert!(matches!(
+            result,
+            Err(KomposeError::ShortcutRegistrationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn failed_switch_keeps_the_old_binding_registered() {
+        let (_, registry, state) = swap_with_conflict();
+        assert_eq!(registry, vec![DEFAULT_SHORTCUT_PRESET_ID.to_string()]);
+        assert!(state.lock_registration().registered);
+    }
+
+    #[test]
+    fn failed_switch_leaves_the_active_preset_unchanged() {
+        let (_, _, state) = swap_with_conflict();
+        assert_eq!(
+            *state.lock_active_presets(),
+            vec![DEFAULT_SHORTCUT_PRESET_ID.to_string()]
+        );
+    }
+
+    #[test]
+    fn failed_switch_rolls_back_bindings_registered_before_the_failure() {
+        let state = CommandBarShortcutState::default();
+        record_shortcut_registration(&state, &Ok(()));
+        let registry = RefCell::new(vec![DEFAULT_SHORTCUT_PRESET_ID.to_string()]);
+        let result = switch_shortcut_presets_with(
+            &state,
+            &["alt_space".to_string(), "ctrl_space".to_string()],
+            false,
+            |preset_id| registry.borrow().iter().any(|id| id == preset_id),
+            |preset_id| {
+                if preset_id == "ctrl_space" {
+                    return Err(KomposeError::ShortcutRegistrationFailed(
+                        "Ctrl + Space is in use.".to_string(),
+                    ));
+                }
+                registry.borrow_mut().push(preset_id.to_string());
+                Ok(())
+            },
+            |preset_id| registry.borrow_mut().retain(|id| id != preset_id),
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            registry.into_inner(),
+            vec![DEFAULT_SHORTCUT_PRESET_ID.to_string()]
+        );
+    }
+
+    #[test]
+    fn switching_presets_recovers_from_a_poisoned_state() {
+        let state = CommandBarShortcutState::default();
+        std::thread::scope(|scope| {
+            let poisoner = scope.spawn(|| {
+                let _active_presets = state.active_presets.lock().unwrap();
+                let _enabled = state.enabled.lock().unwrap();
+                let _registration = state.registration.lock().unwrap();
+                panic!("poison the shortcut state");
+            });
+            assert!(poisoner.join().is_err());
+        });
+        assert!(state.active_presets.is_poisoned());
+
+        let registry = RefCell::new(vec![DEFAULT_SHORTCUT_PRESET_ID.to_string()]);
+        let result = switch_shortcut_presets_with(
+            &state,
+            &["alt_space".to_string()],
+            false,
+            |preset_id| registry.borrow().iter().any(|id| id == preset_id),
+            |preset_id| {
+                registry.borrow_mut().push(preset_id.to_string());
+                Ok(())
+            },
+            |preset_id| registry.borrow_mut().retain(|id| id != preset_id),
+        );
+        assert_eq!(result.unwrap(), vec!["alt_space".to_string()]);
+        assert_eq!(*state.lock_active_presets(), vec!["alt_space".to_string()]);
+        assert!(!state.active_presets.is_poisoned());
+    }
+
+    #[test]
+    fn successful_switch_replaces_the_old_binding() {
+        let state = CommandBarShortcutState::default();
+        let registry = RefCell::new(vec![DEFAULT_SHORTCUT_PRESET_ID.to_string()]);
+        let result = switch_shortcut_presets_with(
+            &state,
+            &["ctrl_space".to_string()],
+            false,
+            |preset_id| registry.borrow().iter().any(|id| id == preset_id),
+            |preset_id| {
+                registry.borrow_mut().push(preset_id.to_string());
+                Ok(())
+            },
+            |preset_id| registry.borrow_mut().retain(|id| id != preset_id),
+        );
+        assert_eq!(result.unwrap(), vec!["ctrl_space".to_string()]);
+        assert_eq!(registry.into_inner(), vec!["ctrl_space".to_string()]);
+        assert_eq!(*state.lock_active_presets(), vec!["ctrl_space".to_string()]);
+    }
+
+    #[test]
+    fn switch_before_onboarding_only_updates_the_active_presets() {
+        let state = CommandBarShortcutState::default();
+        let registry = RefCell::new(Vec::<String>::new());
+        let result = switch_shortcut_presets_with(
+            &state,
+            &["ctrl_space".to_string()],
+            true,
+            |preset_id| registry.borrow().iter().any(|id| id == preset_id),
+            |preset_id| {
+                registry.borrow_mut().push(preset_id.to_string());
+                Ok(())
+            },
+            |preset_id| registry.borrow_mut().retain(|id| id != preset_id),
+        );
+        assert_eq!(result.unwrap(), vec!["ctrl_space".to_string()]);
+        assert!(registry.into_inner().is_empty());
+        assert_eq!(*state.lock_active_presets(), vec!["ctrl_space".to_string()]);
+    }
+
+    #[test]
+    fn update_check_is_due_right_away_when_never_run_or_overdue() {
+        let day = 24 * 60 * 60 * 1000;
+        assert_eq!(update_check_delay(None, 10 * day, 24), Duration::ZERO);
+        assert_eq!(update_check_delay(Some(day), 3 * day, 24), Duration::ZERO);
+    }
+
+    #[test]
+    fn update_check_waits_out_the_rest_of_the_interval() {
+        let hour = 60 * 60 * 1000;
+        assert_eq!(
+            update_check_delay(Some(10 * hour), 16 * hour, 24),
+            Duration::from_secs(18 * 60 * 60)
+        );
+    }
+
+    fn monitor(x: i32, y: i32, width: i32, height: i32) -> MonitorBounds {
+        MonitorBounds {
+            x,
+            y,
+            width,
+            height,
+            scale_factor: 1.0,
+        }
+    }
+
+    #[test]
+    fn position_past_the_right_edge_is_pulled_back_on_screen() {
+        let monitors = [monitor(0, 0, 1920, 1080)];
+        assert_eq!(
+            clamp_to_monitors((1800, 200), (640, 400), &monitors),
+            Some((1280, 200))
+        );
+    }
+
+    #[test]
+    fn position_left_of_every_monitor_snaps_to_the_primary_center() {
+        let monitors = [monitor(0, 0, 1920, 1080)];
+        assert_eq!(
+            clamp_to_monitors((-700, 200), (640, 400), &monitors),
+            Some((640, 340))
+        );
+    }
+
+    #[test]
+    fn position_on_a_disconnected_monitor_snaps_to_the_primary_center() {
+        // Saved on an external display to the right that is no longer attached.
+        let monitors = [monitor(0, 0, 1440, 900)];
+        assert_eq!(
+            clamp_to_monitors((2200, 300), (640, 400), &monitors),
+            Some((400, 250))
+        );
+    }
+
+    #[test]
+    fn position_on_a_secondary_monitor_stays_there() {
+        let monitors = [monitor(0, 0, 1920, 1080), monitor(1920, -200, 2560, 1440)];
+        assert_eq!(
+            clamp_to_monitors((2500, -100), (640, 400), &monitors),
+            Some((2500, -100))
+        );
+    }
+
+    #[test]
+    fn bar_larger_than_the_monitor_is_pinned_to_its_corner() {
+        let monitors = [monitor(0, 0, 800, 600)];
+        assert_eq!(
+            clamp_to_monitors((100, 100), (1000, 700), &monitors),
+            Some((0, 0))
+        );
+        assert_eq!(
+            clamp_to_monitors((5000, 5000), (1000, 700), &monitors),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn no_monitors_gives_no_position() {
+        assert_eq!(clamp_to_monitors((0, 0), (640, 400), &[]), None);
+    }
+
+    #[test]
+    fn shortcut_strings_parse_modifier_aliases_and_keys() {
+        let cases: &[(&str, Modifiers, Code)] = &[
+            (
+                "Cmd+Shift+K",
+                Modifiers::SUPER | Modifiers::SHIFT,
+                Code::KeyK,
+            ),
+            ("Command+J", Modifiers::SUPER, Code::KeyJ),
+            ("Super+Space", Modifiers::SUPER, Code::Space),
+            ("Meta+F5", Modifiers::SUPER, Code::F5),
+            (
+                "Ctrl+Alt+1",
+                Modifiers::CONTROL | Modifiers::ALT,
+                Code::Digit1,
+            ),
+            ("Control+Space", Modifiers::CONTROL, Code::Space),
+            ("Option+Space", Modifiers::ALT, Code::Space),
+            ("Opt+KeyP", Modifiers::ALT, Code::KeyP),
+            ("alt+shift+p", Modifiers::ALT | Modifiers::SHIFT, Code::KeyP),
+            (
+                "K+Shift+Cmd",
+                Modifiers::SUPER | Modifiers::SHIFT,
+                Code::KeyK,
+            ),
+            (
+                "Shift + Ctrl + K",
+                Modifiers::CONTROL | Modifiers::SHIFT,
+                Code::KeyK,
+            ),
+            ("Cmd+Cmd+K", Modifiers::SUPER, Code::KeyK),
+        ];
+        for (combo, mods, code) in cases {
+            assert_eq!(
+                shortcut_from_string(combo),
+                Ok(Shortcut::new(Some(*mods), *code)),
+                "{}",
+                combo
+            );
+        }
+    }
+
+    #[test]
+    fn shortcut_strings_reject_invalid_combos() {
+        let cases: &[(&str, &str)] = &[
+            ("Shift+K", "at least one of super, ctrl, or alt"),
+            ("K", "at least one of super, ctrl, or alt"),
+            ("Cmd+Shift", "doesn't include a key"),
+            ("Cmd+Blorp", "Unknown shortcut key or modifier 'Blorp'"),
+            ("Ctrl+Esc", "Unknown shortcut key or modifier 'Esc'"),
+            ("Cmd+J+K", "more than one key"),
+            ("Cmd++K", "empty key"),
+            ("", "empty key"),
+        ];
+        for (combo, expected) in cases {
+            let error = shortcut_from_string(combo).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", combo, error);
+        }
+    }
+
+    #[test]
+    fn parsed_shortcuts_round_trip_through_their_preset_id() {
+        for combo in ["Cmd+Shift+K", "Ctrl+Alt+Space", "Option+F12", "Meta+Ctrl+7"] {
+            let shortcut = shortcut_from_string(combo).unwrap();
+            let preset_id = custom_shortcut_preset_id(&shortcut);
+            assert!(preset_id.starts_with(CUSTOM_SHORTCUT_PRESET_PREFIX));
+            assert_eq!(shortcut_for_preset(&preset_id), Some(shortcut), "{}", combo);
+        }
+    }
 }
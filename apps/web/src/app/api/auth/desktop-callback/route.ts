@@ -4,6 +4,8 @@ import type { NextRequest } from "next/server";
 import {
   buildDesktopAuthCallbackUrl,
   DESKTOP_DEEP_LINK_SCHEME_QUERY_PARAM,
+  DESKTOP_OAUTH_STATE_QUERY_PARAM,
+  parseDesktopOAuthState,
 } from "@/lib/desktop-deep-link";
 
 /**
@@ -28,8 +30,9 @@ export async function GET(request: NextRequest) {
     });
 
     // Preserve the mode param so the Tauri deep link handler knows
-    // whether this was a sign-in or account-link operation, and preserve the
-    // desktop scheme so the browser returns to the correct installed flavor.
+    // whether this was a sign-in or account-link operation, preserve the
+    // desktop scheme so the browser returns to the correct installed flavor,
+    // and echo the desktop's CSRF state back to it.
     const url = new URL(request.url);
     const mode = url.searchParams.get("mode");
     const provider = url.searchParams.get("provider");
@@ -41,6 +44,9 @@ export async function GET(request: NextRequest) {
       mode,
       provider,
       scheme: desktopScheme,
+      state: parseDesktopOAuthState(
+        url.searchParams.get(DESKTOP_OAUTH_STATE_QUERY_PARAM)
+      ),
     });
 
     // Return an HTML page that auto-redirects to the deep link.
@@ -1,7 +1,11 @@
 import { auth } from "@kompose/auth";
 import { desktopDeepLinkSchemeSchema, env } from "@kompose/env";
 import { type NextRequest, NextResponse } from "next/server";
-import { DESKTOP_DEEP_LINK_SCHEME_QUERY_PARAM } from "@/lib/desktop-deep-link";
+import {
+  DESKTOP_DEEP_LINK_SCHEME_QUERY_PARAM,
+  DESKTOP_OAUTH_STATE_QUERY_PARAM,
+  parseDesktopOAuthState,
+} from "@/lib/desktop-deep-link";
 
 function isGenericOAuthProvider(provider: string) {
   return provider === "whoop";
@@ -23,6 +27,9 @@ async function handleDesktopSignIn(request: NextRequest) {
   const provider = url.searchParams.get("provider");
   const mode = url.searchParams.get("mode"); // "link" or null (sign-in)
   const linkToken = url.searchParams.get("link_token");
+  const desktopState = parseDesktopOAuthState(
+    url.searchParams.get(DESKTOP_OAUTH_STATE_QUERY_PARAM)
+  );
 
   if (!provider) {
     return NextResponse.json(
@@ -42,6 +49,11 @@ async function handleDesktopSignIn(request: NextRequest) {
       provider,
     };
 
+    // The desktop app only accepts a callback carrying the state it issued.
+    if (desktopState) {
+      callbackEntries[DESKTOP_OAUTH_STATE_QUERY_PARAM] = desktopState;
+    }
+
     if (mode === "link" && !linkToken) {
       return NextResponse.json(
         { error: "Missing link token" },
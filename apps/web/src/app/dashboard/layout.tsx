@@ -19,7 +19,10 @@ import {
   applyCommandBarTaskOpenRequest,
   COMMAND_BAR_TASK_OPEN_EVENT,
 } from "@/lib/command-bar-task-routing";
-import { isTauriRuntime } from "@/lib/tauri-desktop";
+import {
+  completeDesktopOnboarding,
+  isTauriRuntime,
+} from "@/lib/tauri-desktop";
 import {
   dashboardResponsiveLayoutAtom,
   dashboardViewportWidthAtom,
@@ -66,6 +69,16 @@ export default function DashboardLayout({
     }
   }, [replace, sessionQuery.status, sessionUser]);
 
+  // Reaching the dashboard signed in ends desktop onboarding.
+  useEffect(() => {
+    if (!(sessionUser && isTauriRuntime())) {
+      return;
+    }
+    completeDesktopOnboarding().catch((error) => {
+      console.warn("Failed to complete desktop onboarding.", error);
+    });
+  }, [sessionUser]);
+
   // Constrained widths use overlay mode for right chat, so docked open must reset.
   useEffect(() => {
     if (responsiveLayout.canDockRightSidebar || !rightSidebarOpen) {
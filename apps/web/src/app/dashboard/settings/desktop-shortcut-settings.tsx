@@ -16,6 +16,7 @@ import {
   applyDesktopCommandBarShortcutPreset,
   type DesktopCommandBarShortcutPresetId,
   desktopCommandBarShortcutPresets,
+  formatDesktopCommandBarCustomShortcut,
   getDesktopCommandBarShortcutPresetId,
   setDesktopCommandBarShortcutPresetId,
 } from "@/lib/tauri-desktop";
@@ -32,6 +33,8 @@ export function DesktopShortcutSettings() {
     },
   });
   const selectedPresetId = form.watch("presetId");
+  const customShortcutLabel =
+    formatDesktopCommandBarCustomShortcut(selectedPresetId);
 
   useMountEffect(() => {
     setIsLoading(true);
@@ -114,6 +117,19 @@ export function DesktopShortcutSettings() {
           </p>
         ) : (
           <div className="grid gap-2">
+            {customShortcutLabel ? (
+              <Button
+                className="justify-between"
+                disabled
+                type="button"
+                variant="secondary"
+              >
+                <span>Custom</span>
+                <span className="font-mono text-muted-foreground text-xs">
+                  {customShortcutLabel}
+                </span>
+              </Button>
+            ) : null}
             {desktopCommandBarShortcutPresets.map((preset) => {
               const isSelected = preset.id === selectedPresetId;
               return (
@@ -47,6 +47,17 @@ export default function DesktopCommandBarClient() {
     };
   }, [setOpen]);
 
+  // Tell Rust the page handles Escape now, so it drops the native fallback
+  // binding and Escape reaches sub-view back navigation.
+  useEffect(() => {
+    if (!isTauriRuntime()) {
+      return;
+    }
+    invoke("command_bar_frontend_ready").catch((error) => {
+      console.warn("Failed to report command bar ready.", error);
+    });
+  }, []);
+
   // Dismiss the command bar via Rust so the previous app is reactivated
   // before the window hides, avoiding a flicker of the main Kompose window.
   useEffect(() => {
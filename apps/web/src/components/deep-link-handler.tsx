@@ -8,7 +8,6 @@ import { useRouter } from "next/navigation";
 import { useCallback, useEffect, useRef } from "react";
 import { toast } from "sonner";
 import { authClient } from "@/lib/auth-client";
-import { getDesktopAuthCallbackPrefix } from "@/lib/desktop-deep-link";
 import { isTauriRuntime } from "@/lib/tauri-desktop";
 
 /**
@@ -22,12 +21,9 @@ import { isTauriRuntime } from "@/lib/tauri-desktop";
 const claimedTokens = new Set<string>();
 
 const DESKTOP_DEEP_LINK_SCHEME = env.NEXT_PUBLIC_DESKTOP_DEEP_LINK_SCHEME;
-const DESKTOP_AUTH_CALLBACK_PREFIX = getDesktopAuthCallbackPrefix(
-  DESKTOP_DEEP_LINK_SCHEME
-);
 const PROCESSED_TOKENS_KEY = `${DESKTOP_DEEP_LINK_SCHEME}:deep-link-processed-tokens`;
 
-/** Record a token so subsequent getCurrent() calls won't re-process it. */
+/** Record a token so a re-delivered callback won't re-process it. */
 function markTokenProcessed(token: string) {
   const raw = localStorage.getItem(PROCESSED_TOKENS_KEY);
   const processedTokens: string[] = raw ? (JSON.parse(raw) as string[]) : [];
@@ -50,6 +46,25 @@ function isTokenAlreadyProcessed(token: string): boolean {
   return (JSON.parse(raw) as string[]).includes(token);
 }
 
+/**
+ * `auth://callback` payload from Rust. `token` is only set when the
+ * callback's CSRF state matched the one `begin_oauth` issued; otherwise
+ * `error` says why.
+ */
+interface DesktopAuthCallback {
+  error: string | null;
+  mode: string | null;
+  provider: string | null;
+  state: string | null;
+  token: string | null;
+}
+
+/** What `take_pending_deep_links` returns: everything buffered before mount. */
+interface PendingDeepLinks {
+  authCallbacks: DesktopAuthCallback[];
+  urls: string[];
+}
+
 async function refreshLinkedAccountQueries(queryClient: QueryClient) {
   await queryClient.invalidateQueries({
     queryKey: LINKED_ACCOUNTS_QUERY_KEY,
@@ -65,13 +80,21 @@ function getLinkedAccountSuccessMessage(linkedProvider: string | null) {
     : "Google account linked.";
 }
 
+/** Non-auth deep links; none are routed inside the app yet. */
+function handleDeepLinkUrls(urls: string[]) {
+  for (const url of urls) {
+    console.info("[DeepLinkHandler] Ignoring unhandled deep link:", url);
+  }
+}
+
 /**
- * Handles desktop deep link URLs in the Tauri app.
+ * Handles desktop deep links in the Tauri app.
  *
- * On receiving `<scheme>://auth/callback?token=TOKEN`, verifies the one-time
- * token via the Better Auth client. The bearer plugin captures the session
- * token from the `set-auth-token` response header and persists it to
- * Tauri Store (via setTauriBearer in auth-client.ts). All subsequent
+ * Rust parses `<scheme>://auth/callback?token=TOKEN` links, checks their
+ * CSRF state, and delivers them as `auth://callback`; this verifies the
+ * one-time token via the Better Auth client. The bearer plugin captures the
+ * session token from the `set-auth-token` response header and persists it
+ * to Tauri Store (via setTauriBearer in auth-client.ts). All subsequent
  * requests use this bearer token via the Authorization header, bypassing
  * WKWebView's ITP cookie restrictions entirely.
  */
@@ -80,24 +103,22 @@ export function DeepLinkHandler() {
   const queryClient = useQueryClient();
   const processingRef = useRef(false);
 
-  const handleDeepLinkUrl = useCallback(
-    async (urlString: string) => {
-      if (!urlString.startsWith(DESKTOP_AUTH_CALLBACK_PREFIX)) {
-        return;
-      }
-
+  const handleAuthCallback = useCallback(
+    async (callback: DesktopAuthCallback) => {
       if (processingRef.current) {
         return;
       }
       processingRef.current = true;
 
       try {
-        const url = new URL(urlString);
-        const token = url.searchParams.get("token");
+        const { token } = callback;
 
         if (!token) {
-          console.warn("[DeepLinkHandler] No token in deep link URL");
-          toast.error("Authentication failed. No token received.");
+          console.warn(
+            "[DeepLinkHandler] Auth callback without a token:",
+            callback.error
+          );
+          toast.error("Authentication failed. Please try signing in again.");
           return;
         }
 
@@ -135,8 +156,8 @@ export function DeepLinkHandler() {
         // Remember this token so we don't re-process it.
         markTokenProcessed(token);
 
-        const isLinkMode = url.searchParams.get("mode") === "link";
-        const linkedProvider = url.searchParams.get("provider");
+        const isLinkMode = callback.mode === "link";
+        const linkedProvider = callback.provider;
 
         if (isLinkMode) {
           await refreshLinkedAccountQueries(queryClient);
@@ -167,29 +188,41 @@ export function DeepLinkHandler() {
 
     const setup = async () => {
       try {
-        const { getCurrent, onOpenUrl } = await import(
-          "@tauri-apps/plugin-deep-link"
+        const { invoke } = await import("@tauri-apps/api/core");
+        const { listen } = await import("@tauri-apps/api/event");
+
+        // Rust is the single source of deep links, live once the startup
+        // queue below has been drained. OAuth callbacks only ever arrive as
+        // validated `auth://callback` events, never as raw URLs.
+        const unlistenAuthCallback = await listen<DesktopAuthCallback>(
+          "auth://callback",
+          (event) => {
+            handleAuthCallback(event.payload);
+          }
         );
-
-        // Check if the app was launched via a deep link.
-        const startUrls = await getCurrent();
-        if (startUrls && startUrls.length > 0) {
-          for (const url of startUrls) {
-            await handleDeepLinkUrl(url);
+        const unlistenDeepLink = await listen<string[]>(
+          "deep-link://received",
+          (event) => {
+            handleDeepLinkUrls(event.payload);
           }
-        }
+        );
 
-        // Listen for deep link events while the app is running.
-        const unlisten = await onOpenUrl((urls) => {
-          for (const url of urls) {
-            handleDeepLinkUrl(url);
-          }
-        });
+        cleanupFn = () => {
+          unlistenAuthCallback();
+          unlistenDeepLink();
+        };
 
-        cleanupFn = unlisten;
+        // Drain what Rust buffered before this mounted (e.g. the app was
+        // launched via an OAuth callback link).
+        const pending = await invoke<PendingDeepLinks>(
+          "take_pending_deep_links"
+        );
+        for (const callback of pending.authCallbacks) {
+          await handleAuthCallback(callback);
+        }
+        handleDeepLinkUrls(pending.urls);
       } catch (error) {
-        // Deep link plugin may not be available in dev mode.
-        console.warn("[DeepLinkHandler] Plugin not available:", error);
+        console.warn("[DeepLinkHandler] Failed to set up deep links:", error);
       }
     };
 
@@ -198,7 +231,7 @@ export function DeepLinkHandler() {
     return () => {
       cleanupFn?.();
     };
-  }, [handleDeepLinkUrl]);
+  }, [handleAuthCallback]);
 
   return null;
 }
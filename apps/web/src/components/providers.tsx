@@ -19,7 +19,6 @@ import {
   initTauriBearer,
   isTauriRuntime,
   openUrlInDesktopBrowser,
-  syncDesktopCommandBarShortcutPreset,
 } from "@/lib/tauri-desktop";
 import { isToastSuppressedPath } from "@/lib/toast-suppression";
 import { createAppQueryClient, orpc } from "@/utils/orpc";
@@ -102,19 +101,6 @@ function TauriBearerInit({ children }: { children: React.ReactNode }) {
 }
 
 function TauriDesktopBridgeBootstrap() {
-  useMountEffect(() => {
-    if (!isTauriRuntime()) {
-      return;
-    }
-
-    syncDesktopCommandBarShortcutPreset().catch((error) => {
-      console.warn(
-        "Failed to sync desktop command bar shortcut preset.",
-        error
-      );
-    });
-  });
-
   useMountEffect(() => {
     if (!isTauriRuntime()) {
       return;
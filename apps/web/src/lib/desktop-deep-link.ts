@@ -1,6 +1,17 @@
 import type { DesktopDeepLinkScheme } from "@kompose/env";
 
 export const DESKTOP_DEEP_LINK_SCHEME_QUERY_PARAM = "desktop_scheme";
+/**
+ * Carries the desktop app's CSRF state (from its `begin_oauth` command)
+ * through the browser flow; it comes back as `state` on the callback link.
+ */
+export const DESKTOP_OAUTH_STATE_QUERY_PARAM = "desktop_state";
+const DESKTOP_OAUTH_STATE_PATTERN = /^[0-9a-f]{32}$/;
+
+/** The desktop OAuth state from a query param, or null when malformed. */
+export function parseDesktopOAuthState(value: string | null): string | null {
+  return value && DESKTOP_OAUTH_STATE_PATTERN.test(value) ? value : null;
+}
 
 export function getDesktopAuthCallbackPrefix(
   scheme: DesktopDeepLinkScheme
@@ -17,6 +28,7 @@ export function buildDesktopAuthCallbackUrl(options: {
   mode?: string | null;
   provider?: string | null;
   scheme: DesktopDeepLinkScheme;
+  state?: string | null;
 }): string {
   const callbackUrl = new URL(getDesktopAuthCallbackPrefix(options.scheme));
 
@@ -30,5 +42,9 @@ export function buildDesktopAuthCallbackUrl(options: {
     callbackUrl.searchParams.set("provider", options.provider);
   }
 
+  if (options.state) {
+    callbackUrl.searchParams.set("state", options.state);
+  }
+
   return callbackUrl.toString();
 }
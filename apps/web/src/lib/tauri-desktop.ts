@@ -2,7 +2,10 @@
 
 import { env } from "@kompose/env";
 import { toast } from "sonner";
-import { DESKTOP_DEEP_LINK_SCHEME_QUERY_PARAM } from "@/lib/desktop-deep-link";
+import {
+  DESKTOP_DEEP_LINK_SCHEME_QUERY_PARAM,
+  DESKTOP_OAUTH_STATE_QUERY_PARAM,
+} from "@/lib/desktop-deep-link";
 
 interface AuthErrorResult {
   error?: {
@@ -11,14 +14,22 @@ interface AuthErrorResult {
   } | null;
 }
 
-export type DesktopCommandBarShortcutPresetId =
+type DesktopCommandBarBuiltInShortcutPresetId =
   | "cmd_or_ctrl_shift_k"
   | "ctrl_space"
   | "alt_space";
 
+/**
+ * A built-in preset, or a user-defined binding stored by the desktop app as
+ * `custom:<modifiers>+<key>` (e.g. `custom:ctrl+alt+KeyJ`).
+ */
+export type DesktopCommandBarShortcutPresetId =
+  | DesktopCommandBarBuiltInShortcutPresetId
+  | `custom:${string}`;
+
 interface DesktopCommandBarShortcutPreset {
   accelerator: string;
-  id: DesktopCommandBarShortcutPresetId;
+  id: DesktopCommandBarBuiltInShortcutPresetId;
   label: string;
 }
 
@@ -45,13 +56,37 @@ const DEFAULT_DESKTOP_COMMAND_BAR_SHORTCUT_PRESET_ID: DesktopCommandBarShortcutP
   "cmd_or_ctrl_shift_k";
 const DESKTOP_SETTINGS_STORE_FILE = "desktop-settings.json";
 const COMMAND_BAR_SHORTCUT_PRESET_STORE_KEY = "command-bar-shortcut-preset-id";
+const CUSTOM_SHORTCUT_PRESET_PREFIX = "custom:";
 
 function isDesktopCommandBarShortcutPresetId(
   value: string
 ): value is DesktopCommandBarShortcutPresetId {
+  if (value.startsWith(CUSTOM_SHORTCUT_PRESET_PREFIX)) {
+    return value.length > CUSTOM_SHORTCUT_PRESET_PREFIX.length;
+  }
   return desktopCommandBarShortcutPresets.some((preset) => preset.id === value);
 }
 
+/**
+ * Display label for a custom preset id, e.g. `custom:ctrl+alt+KeyJ` becomes
+ * `Ctrl + Alt + J`. Returns null for built-in presets.
+ */
+export function formatDesktopCommandBarCustomShortcut(
+  presetId: DesktopCommandBarShortcutPresetId
+): string | null {
+  if (!presetId.startsWith(CUSTOM_SHORTCUT_PRESET_PREFIX)) {
+    return null;
+  }
+  return presetId
+    .slice(CUSTOM_SHORTCUT_PRESET_PREFIX.length)
+    .split("+")
+    .map((token) => {
+      const key = token.replace(/^(Key|Digit)(?=.$)/, "");
+      return key.charAt(0).toUpperCase() + key.slice(1);
+    })
+    .join(" + ");
+}
+
 // Detect whether code runs inside a Tauri WebView.
 export function isTauriRuntime() {
   return typeof window !== "undefined" && "__TAURI_INTERNALS__" in window;
@@ -114,6 +149,14 @@ export async function openDesktopOAuth(
     env.NEXT_PUBLIC_DESKTOP_DEEP_LINK_SCHEME
   );
 
+  // The desktop app hands out a one-use CSRF state and only passes the
+  // callback's token on to `DeepLinkHandler` when the same state comes back.
+  if (isTauriRuntime()) {
+    const { invoke } = await import("@tauri-apps/api/core");
+    const oauthState = await invoke<string>("begin_oauth");
+    signInUrl.searchParams.set(DESKTOP_OAUTH_STATE_QUERY_PARAM, oauthState);
+  }
+
   if (mode === "link") {
     // Generate a one-time token tied to the current session. The bearer
     // token is sent automatically via the Authorization header.
@@ -191,12 +234,18 @@ export async function applyDesktopCommandBarShortcutPreset(
 }
 
 /**
- * Load the persisted preset and apply it at runtime.
+ * Finish desktop onboarding the first time a signed-in user reaches the app,
+ * so the desktop shell registers the command bar shortcut it held back.
  */
-export async function syncDesktopCommandBarShortcutPreset(): Promise<DesktopCommandBarShortcutPresetId> {
-  const presetId = await getDesktopCommandBarShortcutPresetId();
-  await applyDesktopCommandBarShortcutPreset(presetId);
-  return presetId;
+export async function completeDesktopOnboarding(): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+  const { invoke } = await import("@tauri-apps/api/core");
+  const isFirstRun = await invoke<boolean>("is_first_run");
+  if (isFirstRun) {
+    await invoke("mark_onboarding_complete");
+  }
 }
 
 // ---------------------------------------------------------------------------